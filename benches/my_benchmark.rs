@@ -1,5 +1,12 @@
+use allocator::backoff::Backoff;
+use allocator::backoff::ExponentialSpin;
+use allocator::backoff::Spin;
+use allocator::backoff::SpinThenPark;
+use allocator::backoff::SpinThenYield;
+use allocator::pool_allocator::PoolAllocator;
 use criterion::criterion_group;
 use criterion::criterion_main;
+use criterion::Bencher;
 use criterion::Criterion;
 use crossbeam_utils as cu;
 use std::sync::atomic::Ordering::Acquire;
@@ -67,6 +74,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         .unwrap();
     });
 
+    #[cfg(feature = "parking_lot")]
     group.bench_function("safe::parking_lot{4}", |b| {
         let a =
             allocator::s::basic::parking_lot::Allocator::<i64>::new(4);
@@ -98,6 +106,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         .unwrap();
     });
 
+    #[cfg(feature = "simple_mutex")]
     group.bench_function("safe::simple_mutex{4}", |b| {
         let a =
             allocator::s::basic::simple_mutex::Allocator::<i64>::new(4);
@@ -129,6 +138,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         .unwrap();
     });
 
+    #[cfg(feature = "antidote")]
     group.bench_function("safe::antidote{4}", |b| {
         let a = allocator::s::basic::antidote::Allocator::<i64>::new(4);
         let repeat = std::sync::atomic::AtomicBool::new(true);
@@ -309,6 +319,10 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         .unwrap();
     });
 
+    group.bench_function("unsafe::v3{4}", |b| {
+        contended::<allocator::u::v3::Allocator<i64>>(b)
+    });
+
     group.bench_function("unsafe::v4{4}", |b| {
         let a = allocator::u::v4::Allocator::<i64>::new(4);
         let repeat = std::sync::atomic::AtomicBool::new(true);
@@ -350,5 +364,52 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn contended<A: PoolAllocator<i64> + Sync>(b: &mut Bencher) {
+    let a = A::new(4);
+    let repeat = std::sync::atomic::AtomicBool::new(true);
+
+    cu::thread::scope(|s| {
+        for id in 0..3 {
+            let (a, repeat) = (&a, &repeat);
+
+            s.spawn(move |_| {
+                while repeat.load(Acquire) {
+                    std::mem::drop(a.box_it(id));
+                }
+            });
+        }
+
+        b.iter(|| a.box_it(3));
+
+        repeat.store(false, Release);
+    })
+    .unwrap();
+}
+
+fn backoff<B: Backoff + 'static>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(format!("backoff::{}", name));
+
+    group.bench_function("safe::v3{4}", |b| {
+        contended::<allocator::s::advanced::v3::Allocator<i64, B>>(b)
+    });
+
+    group.bench_function("unsafe::v3{4}", |b| {
+        contended::<allocator::u::v3::Allocator<i64, B>>(b)
+    });
+
+    group.bench_function("unsafe::v4{4}", |b| {
+        contended::<allocator::u::v4::Allocator<i64, B>>(b)
+    });
+
+    group.finish();
+}
+
+pub fn backoff_benchmark(c: &mut Criterion) {
+    backoff::<Spin>(c, "spin");
+    backoff::<ExponentialSpin>(c, "exponential_spin");
+    backoff::<SpinThenYield>(c, "spin_then_yield");
+    backoff::<SpinThenPark>(c, "spin_then_park");
+}
+
+criterion_group!(benches, criterion_benchmark, backoff_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,11 @@
+#![no_main]
+
+#[path = "../harness.rs"]
+mod harness;
+
+use allocator::s::basic::antidote::Allocator;
+use harness::Tracked;
+
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    harness::run::<Allocator<Tracked>>(data)
+});
@@ -0,0 +1,164 @@
+//! Decodes arbitrary bytes into boxing, writing, dropping and
+//! exhaustion events for one allocator, and checks its invariants
+//! after every event. Shared by the cargo-fuzz targets and by
+//! `tests/fuzz.rs`, which replays the corpus.
+//!
+//! The first byte picks the capacity. Every following byte picks an
+//! event, some of which read another byte or two as their argument:
+//!
+//! * `0`: box the next byte
+//! * `1`: write the byte after next into the live box picked by the
+//!   next byte
+//! * `2`: drop the live box picked by the next byte
+//! * `3`: box values until the allocator is exhausted, then drop them
+
+use allocator::pool_allocator::PoolAllocator;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::mem::drop;
+use std::rc::Rc;
+
+const MAX_CAPACITY: usize = 16;
+
+/// A value that records its drops in a table shared by all values of a
+/// run, so that each can be checked to be dropped exactly once.
+pub struct Tracked {
+    id: usize,
+    value: u8,
+    drops: Rc<RefCell<Vec<u32>>>,
+}
+
+impl Tracked {
+    fn new(value: u8, drops: &Rc<RefCell<Vec<u32>>>) -> Self {
+        let mut table = drops.borrow_mut();
+        table.push(0);
+
+        Self {
+            id: table.len() - 1,
+            value,
+            drops: drops.clone(),
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.drops.borrow_mut()[self.id] += 1;
+    }
+}
+
+pub fn run<A: PoolAllocator<Tracked>>(data: &[u8]) {
+    let mut bytes = data.iter().copied();
+    let capacity = match bytes.next() {
+        Some(byte) => usize::from(byte) % MAX_CAPACITY + 1,
+        None => return,
+    };
+
+    let drops = Rc::new(RefCell::new(Vec::new()));
+    let allocator = A::new(capacity);
+    // Every live box, next to the value it has to hold.
+    let mut live: Vec<(A::Box<'_>, u8)> = Vec::new();
+
+    while let Some(event) = bytes.next() {
+        match event % 4 {
+            0 => {
+                let value = bytes.next().unwrap_or(0);
+                let tracked = Tracked::new(value, &drops);
+                let id = tracked.id;
+
+                match allocator.try_box_it(tracked) {
+                    Ok(b) => {
+                        assert!(
+                            live.len() < capacity,
+                            "box beyond capacity"
+                        );
+                        live.push((b, value));
+                    }
+                    Err(e) => {
+                        assert_eq!(
+                            capacity,
+                            live.len(),
+                            "spurious exhaustion"
+                        );
+                        assert_eq!(id, e.into_inner().id);
+                    }
+                }
+            }
+            1 => {
+                let index = bytes.next().unwrap_or(0);
+                let value = bytes.next().unwrap_or(0);
+
+                if !live.is_empty() {
+                    let index = usize::from(index) % live.len();
+                    let (b, expected) = &mut live[index];
+                    b.value = value;
+                    *expected = value;
+                }
+            }
+            2 => {
+                let index = bytes.next().unwrap_or(0);
+
+                if !live.is_empty() {
+                    live.swap_remove(usize::from(index) % live.len());
+                }
+            }
+            _ => {
+                let fillers: Vec<_> = (live.len()..capacity)
+                    .map(|_| {
+                        let tracked = Tracked::new(0, &drops);
+
+                        match allocator.try_box_it(tracked) {
+                            Ok(b) => b,
+                            Err(_) => panic!("free list too short"),
+                        }
+                    })
+                    .collect();
+
+                let overflow = Tracked::new(0, &drops);
+                assert!(
+                    allocator.try_box_it(overflow).is_err(),
+                    "free list too long"
+                );
+                assert_distinct(
+                    live.iter()
+                        .map(|(b, _)| &**b)
+                        .chain(fillers.iter().map(|b| &**b)),
+                );
+                drop(fillers);
+            }
+        }
+
+        check(&live, &drops.borrow());
+    }
+
+    drop(live);
+    drop(allocator);
+    let table = drops.borrow();
+    assert!(table.iter().all(|&count| count == 1), "{:?}", table);
+}
+
+fn check<B>(live: &[(B, u8)], drops: &[u32])
+where
+    B: std::ops::Deref<Target = Tracked>,
+{
+    assert_distinct(live.iter().map(|(b, _)| &**b));
+
+    for (b, expected) in live {
+        assert_eq!(*expected, b.value, "value overwritten");
+        assert_eq!(0, drops[b.id], "live value dropped");
+    }
+
+    let dropped = drops.iter().filter(|&&count| count != 0).count();
+    assert_eq!(drops.len(), live.len() + dropped, "value leaked");
+    assert!(drops.iter().all(|&count| count <= 1), "dropped twice");
+}
+
+// Two live boxes at the same address mean a slot was handed out twice.
+fn assert_distinct<'a>(boxes: impl Iterator<Item = &'a Tracked>) {
+    let mut addresses = HashSet::new();
+
+    for tracked in boxes {
+        let address = tracked as *const Tracked;
+        assert!(addresses.insert(address), "slot handed out twice");
+    }
+}
@@ -2,7 +2,7 @@
 #[repr(align(128))]
 pub struct Align128<T>(pub T);
 
-impl<T> std::ops::Deref for Align128<T> {
+impl<T> core::ops::Deref for Align128<T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -10,17 +10,20 @@ impl<T> std::ops::Deref for Align128<T> {
     }
 }
 
-impl<T> std::ops::DerefMut for Align128<T> {
+impl<T> core::ops::DerefMut for Align128<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
-impl<T> std::fmt::Debug for Align128<T>
+impl<T> core::fmt::Debug for Align128<T>
 where
-    T: std::fmt::Debug,
+    T: core::fmt::Debug,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Debug::fmt(&self.0, f)
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+    ) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&self.0, f)
     }
 }
@@ -0,0 +1,95 @@
+#[cfg(all(test, feature = "std"))]
+mod tests;
+
+#[cfg(feature = "std")]
+use crate::sync::park_timeout;
+use crate::sync::spin_loop;
+#[cfg(feature = "std")]
+use crate::sync::yield_now;
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+const SPIN_LIMIT: u32 = 6;
+#[cfg(feature = "std")]
+const YIELD_LIMIT: u32 = 10;
+
+/// What a retry loop does after a failed attempt. Every operation
+/// starts out with a fresh `Default` value, so implementations can
+/// escalate the longer an operation keeps failing.
+pub trait Backoff: Default {
+    fn backoff(&mut self);
+}
+
+/// Issues a single spin-loop hint and retries right away.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Spin;
+
+impl Backoff for Spin {
+    fn backoff(&mut self) {
+        spin_loop();
+    }
+}
+
+/// Spins twice as long after every failure, up to 64 spin-loop hints.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExponentialSpin {
+    step: u32,
+}
+
+impl Backoff for ExponentialSpin {
+    fn backoff(&mut self) {
+        spin(self.step);
+        self.step = (self.step + 1).min(SPIN_LIMIT);
+    }
+}
+
+/// Spins exponentially at first, then yields the time slice, which
+/// suits oversubscribed machines.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpinThenYield {
+    step: u32,
+}
+
+#[cfg(feature = "std")]
+impl Backoff for SpinThenYield {
+    fn backoff(&mut self) {
+        if self.step < SPIN_LIMIT {
+            spin(self.step);
+            self.step += 1;
+        } else {
+            yield_now();
+        }
+    }
+}
+
+/// Spins and yields like [`SpinThenYield`], then parks the thread for
+/// exponentially longer periods of up to about a millisecond.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpinThenPark {
+    step: u32,
+}
+
+#[cfg(feature = "std")]
+impl Backoff for SpinThenPark {
+    fn backoff(&mut self) {
+        if self.step < SPIN_LIMIT {
+            spin(self.step);
+        } else if self.step < YIELD_LIMIT {
+            yield_now();
+        } else {
+            let exponent =
+                (self.step - YIELD_LIMIT).min(SPIN_LIMIT + 4);
+            park_timeout(Duration::from_micros(1 << exponent));
+        }
+
+        self.step = self.step.saturating_add(1);
+    }
+}
+
+fn spin(exponent: u32) {
+    for _ in 0..1 << exponent {
+        spin_loop();
+    }
+}
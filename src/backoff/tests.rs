@@ -0,0 +1,42 @@
+use super::Backoff;
+
+#[test]
+fn spinning_never_sleeps() {
+    // `Spin` has no state to escalate with in the first place.
+    assert_eq!(0, std::mem::size_of::<super::Spin>());
+    let mut backoff = super::ExponentialSpin::default();
+
+    for _ in 0..1_000 {
+        backoff.backoff();
+        assert!(backoff.step <= super::SPIN_LIMIT);
+    }
+}
+
+#[test]
+fn exponential_spin_stops_escalating() {
+    let mut backoff = super::ExponentialSpin::default();
+
+    for _ in 0..1_000 {
+        backoff.backoff();
+    }
+
+    assert_eq!(super::SPIN_LIMIT, backoff.step);
+}
+
+#[test]
+fn parking_escalates() {
+    let mut backoff = super::SpinThenPark::default();
+
+    // Spins, then yields, but does not park yet.
+    for step in 0..super::YIELD_LIMIT {
+        assert_eq!(step, backoff.step);
+        backoff.backoff();
+    }
+
+    // Parks from here on, without the step overflowing.
+    assert_eq!(super::YIELD_LIMIT, backoff.step);
+    backoff.step = u32::MAX - 1;
+    backoff.backoff();
+    backoff.backoff();
+    assert_eq!(u32::MAX, backoff.step);
+}
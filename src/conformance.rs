@@ -0,0 +1,276 @@
+//! The tests every allocator has to pass, instantiated in the `tests`
+//! module of each variant with
+//!
+//! ```ignore
+//! crate::conformance::suite!(super::Allocator::new, Lifo);
+//! ```
+//!
+//! The first argument creates an allocator from a capacity. It is
+//! pasted into every test, so a generic constructor like
+//! `Allocator::new` is instantiated for each item type separately.
+//!
+//! Allocators that can grow also pass their constructor from an initial
+//! and a maximum capacity:
+//!
+//! ```ignore
+//! crate::conformance::suite!(
+//!     super::Allocator::new,
+//!     Lifo,
+//!     Growable(super::Allocator::growable)
+//! );
+//! ```
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// The order in which released slots are handed out again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Reuse {
+    /// The slot released last, as with a free list.
+    Lifo,
+    /// The first free slot in storage order, as with a scan.
+    InOrder,
+    /// Any free slot.
+    Unspecified,
+}
+
+/// Counts how often it has been dropped.
+pub(crate) struct Counted(pub(crate) usize, pub(crate) Rc<Cell<usize>>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.1.set(self.1.get() + 1);
+    }
+}
+
+/// Panics when dropped, if armed.
+pub(crate) struct Bomb(pub(crate) bool);
+
+impl Drop for Bomb {
+    fn drop(&mut self) {
+        if self.0 {
+            panic!("boom");
+        }
+    }
+}
+
+macro_rules! suite {
+    ($new:expr, $reuse:ident, Growable($growable:expr)) => {
+        $crate::conformance::suite!($new, $reuse);
+
+        #[test]
+        fn grows_up_to_max_capacity() {
+            let a = ($growable)(2, 5);
+            let first = a.box_it(0_i64);
+            let address = &*first as *const i64;
+            let boxes: Vec<_> = (1..5).map(|i| a.box_it(i)).collect();
+            assert!(a.try_box_it(5).is_err());
+            assert_eq!(address, &*first as *const i64);
+            assert_eq!(0, *first);
+
+            for (i, b) in (1..).zip(&boxes) {
+                assert_eq!(i, **b);
+            }
+
+            ::std::mem::drop((first, boxes));
+            let boxes: Vec<_> = (0..5).map(|i| a.box_it(i)).collect();
+            assert!(a.try_box_it(5).is_err());
+            ::std::mem::drop(boxes);
+        }
+    };
+    ($new:expr, $reuse:ident) => {
+        #[test]
+        fn equality() {
+            let a = ($new)(1);
+            let b = a.box_it(123_i64);
+            assert_eq!(123, *b);
+        }
+
+        #[test]
+        #[should_panic]
+        fn panic_when_out_of_memory() {
+            let a = ($new)(1);
+            let b = a.box_it(123_i64);
+            let c = a.box_it(234);
+            ::std::mem::drop((b, c));
+        }
+
+        #[test]
+        fn memory_reclamation() {
+            let a = ($new)(1);
+            let b = a.box_it(123_i64);
+            ::std::mem::drop(b);
+            let c = a.box_it(234);
+            assert_eq!(234, *c);
+        }
+
+        #[test]
+        fn error_when_out_of_memory() {
+            let a = ($new)(1);
+            let b = a.try_box_it(123_i64).ok().unwrap();
+            let c = a.try_box_it(234);
+            assert_eq!(234, c.err().unwrap().into_inner());
+            ::std::mem::drop(b);
+            assert_eq!(345, *a.box_it(345));
+        }
+
+        #[test]
+        // Not every allocator implements `Drop`.
+        #[allow(clippy::drop_non_drop)]
+        fn drops_every_value_once() {
+            use $crate::conformance::Counted;
+
+            let drops = ::std::rc::Rc::new(::std::cell::Cell::new(0));
+            let a = ($new)(3);
+            let boxes: Vec<_> = (0..3)
+                .map(|i| a.box_it(Counted(i, drops.clone())))
+                .collect();
+            let error =
+                a.try_box_it(Counted(3, drops.clone())).err().unwrap();
+            assert_eq!(0, drops.get());
+            assert_eq!(3, error.into_inner().0);
+            assert_eq!(1, drops.get());
+
+            for (i, b) in boxes.iter().enumerate() {
+                assert_eq!(i, b.0);
+            }
+
+            let mut boxes = boxes.into_iter();
+            ::std::mem::drop(boxes.next());
+            assert_eq!(2, drops.get());
+            ::std::mem::drop(boxes);
+            assert_eq!(4, drops.get());
+            ::std::mem::drop(a);
+            assert_eq!(4, drops.get());
+        }
+
+        #[test]
+        fn fill_and_drain() {
+            let a = ($new)(8);
+
+            for round in 0..3_i64 {
+                let boxes: Vec<_> =
+                    (0..8).map(|i| a.box_it(round * 8 + i)).collect();
+                assert!(a.try_box_it(-1).is_err());
+                let addresses: ::std::collections::HashSet<_> =
+                    boxes.iter().map(|b| &**b as *const i64).collect();
+                assert_eq!(8, addresses.len());
+
+                for (i, b) in (0..).zip(&boxes) {
+                    assert_eq!(round * 8 + i, **b);
+                }
+            }
+        }
+
+        #[test]
+        fn reuse_order() {
+            let a = ($new)(3);
+            let mut boxes: Vec<_> =
+                (0..3_i64).map(|i| a.box_it(i)).collect();
+            let addresses: Vec<_> =
+                boxes.iter().map(|b| &**b as *const i64).collect();
+            ::std::mem::drop(boxes.remove(0));
+            ::std::mem::drop(boxes.pop());
+            let first = a.box_it(3);
+            let second = a.box_it(4);
+            let reused = [&*first as *const _, &*second as *const _];
+
+            use $crate::conformance::Reuse;
+
+            match Reuse::$reuse {
+                Reuse::Lifo => {
+                    assert_eq!([addresses[2], addresses[0]], reused)
+                }
+                Reuse::InOrder => {
+                    assert_eq!([addresses[0], addresses[2]], reused)
+                }
+                Reuse::Unspecified => {
+                    assert!(reused.contains(&addresses[0]));
+                    assert!(reused.contains(&addresses[2]));
+                }
+            }
+
+            assert_eq!((1, 3, 4), (*boxes[0], *first, *second));
+        }
+
+        #[test]
+        fn panic_in_drop() {
+            use $crate::conformance::Bomb;
+
+            let a = ($new)(1);
+            let b = a.box_it(Bomb(true));
+            let result = ::std::panic::catch_unwind(
+                ::std::panic::AssertUnwindSafe(|| ::std::mem::drop(b)),
+            );
+            assert!(result.is_err());
+            let b = a.try_box_it(Bomb(false)).ok().unwrap();
+            assert!(!b.0);
+        }
+
+        #[test]
+        fn churn() {
+            let a = ($new)(2);
+
+            ::std::thread::scope(|s| {
+                for t in 0..4_i64 {
+                    let a = &a;
+
+                    s.spawn(move || {
+                        for i in 0..1_000 {
+                            let mut value = t;
+
+                            let mut b = loop {
+                                match a.try_box_it(value) {
+                                    Ok(b) => break b,
+                                    Err(e) => value = e.into_inner(),
+                                }
+
+                                ::std::thread::yield_now();
+                            };
+
+                            *b += i;
+                            assert_eq!(t + i, *b);
+                        }
+                    });
+                }
+            });
+        }
+
+        #[test]
+        fn zero_sized() {
+            static DROPS: ::std::sync::atomic::AtomicUsize =
+                ::std::sync::atomic::AtomicUsize::new(0);
+
+            struct Zst;
+
+            impl Drop for Zst {
+                fn drop(&mut self) {
+                    DROPS.fetch_add(
+                        1,
+                        ::std::sync::atomic::Ordering::Relaxed,
+                    );
+                }
+            }
+
+            let a = ($new)(4);
+            let boxes: Vec<_> = (0..4).map(|_| a.box_it(Zst)).collect();
+            assert!(a.try_box_it(Zst).is_err());
+            assert_eq!(
+                1,
+                DROPS.load(::std::sync::atomic::Ordering::Relaxed)
+            );
+            ::std::mem::drop(boxes);
+            assert_eq!(
+                5,
+                DROPS.load(::std::sync::atomic::Ordering::Relaxed)
+            );
+            let a = ($new)(4);
+            let units: Vec<_> = (0..4).map(|_| a.box_it(())).collect();
+            assert!(a.try_box_it(()).is_err());
+            ::std::mem::drop(units);
+            assert_eq!((), *a.box_it(()));
+        }
+    };
+}
+
+pub(crate) use suite;
@@ -0,0 +1,26 @@
+use core::fmt;
+
+/// Returned by `try_box_it` when every slot is in use; carries the
+/// rejected value back to the caller.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct OutOfMemory<T>(pub T);
+
+impl<T> OutOfMemory<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for OutOfMemory<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OutOfMemory(..)")
+    }
+}
+
+impl<T> fmt::Display for OutOfMemory<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("out of reserved memory")
+    }
+}
+
+impl<T> core::error::Error for OutOfMemory<T> {}
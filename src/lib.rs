@@ -1,7 +1,25 @@
-#![feature(thread_id_value)]
-#![feature(maybe_uninit_ref)]
-#![feature(maybe_uninit_extra)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 pub mod align128;
+pub mod backoff;
+#[cfg(all(test, feature = "std"))]
+mod conformance;
+pub mod error;
+#[cfg(all(test, feature = "std"))]
+mod executor;
+#[cfg(feature = "std")]
+pub mod owned;
+#[cfg(feature = "std")]
+pub mod pool_allocator;
+#[cfg(feature = "std")]
 pub mod s;
+#[cfg(feature = "std")]
+pub mod shard;
+mod sync;
 pub mod u;
+#[cfg(feature = "std")]
+pub mod wait;
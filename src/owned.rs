@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests;
+
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+
+/// A box that shares ownership of its allocator instead of borrowing
+/// it, so it is `'static` and can outlive the scope that created it.
+pub struct OwnedBox<A, T>
+where
+    A: PoolAllocator<T> + 'static,
+{
+    // Fields are dropped in declaration order, so the slot is released
+    // before the last reference to the allocator can go away.
+    inner: A::Box<'static>,
+    allocator: Arc<A>,
+}
+
+impl<A, T> OwnedBox<A, T>
+where
+    A: PoolAllocator<T> + 'static,
+{
+    pub(crate) fn try_new(
+        allocator: &Arc<A>,
+        value: T,
+    ) -> Result<Self, OutOfMemory<T>> {
+        // SAFETY: the allocator stays at this address for as long as
+        // `self.allocator` keeps it alive, which outlasts `inner`.
+        let static_allocator = unsafe { &*Arc::as_ptr(allocator) };
+
+        Ok(Self {
+            inner: static_allocator.try_box_it(value)?,
+            allocator: Arc::clone(allocator),
+        })
+    }
+
+    pub fn allocator(this: &Self) -> &Arc<A> {
+        &this.allocator
+    }
+}
+
+impl<A, T> Deref for OwnedBox<A, T>
+where
+    A: PoolAllocator<T> + 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<A, T> DerefMut for OwnedBox<A, T>
+where
+    A: PoolAllocator<T> + 'static,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
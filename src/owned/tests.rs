@@ -0,0 +1,85 @@
+use crate::pool_allocator::PoolAllocator;
+use crate::s;
+use crate::u;
+use std::mem::drop;
+use std::sync::mpsc;
+use std::sync::Arc;
+
+fn keeps_allocator_alive<A: PoolAllocator<i64> + 'static>() {
+    let a = Arc::new(A::new(1));
+    let mut b = a.box_it_owned(123);
+    drop(a);
+    *b += 1;
+    assert_eq!(124, *b);
+
+    let a = Arc::clone(super::OwnedBox::allocator(&b));
+    assert!(a.try_box_it_owned(234).is_err());
+    drop(b);
+    assert_eq!(234, *a.box_it_owned(234));
+}
+
+fn sent_across_threads<A>()
+where
+    A: PoolAllocator<i64> + Send + Sync + 'static,
+    A::Box<'static>: Send,
+{
+    let a = Arc::new(A::new(2));
+    let (sender, receiver) = mpsc::channel();
+
+    let producer = std::thread::spawn({
+        let a = Arc::clone(&a);
+        move || sender.send(a.box_it_owned(123)).unwrap()
+    });
+
+    let b = std::thread::spawn(move || receiver.recv().unwrap())
+        .join()
+        .unwrap();
+
+    producer.join().unwrap();
+    drop(a);
+    assert_eq!(123, *b);
+}
+
+#[test]
+fn s_basic() {
+    #[cfg(feature = "antidote")]
+    keeps_allocator_alive::<s::basic::antidote::Allocator<i64>>();
+    #[cfg(feature = "parking_lot")]
+    keeps_allocator_alive::<s::basic::parking_lot::Allocator<i64>>();
+    #[cfg(feature = "simple_mutex")]
+    keeps_allocator_alive::<s::basic::simple_mutex::Allocator<i64>>();
+    keeps_allocator_alive::<s::basic::std::Allocator<i64>>();
+    #[cfg(feature = "antidote")]
+    sent_across_threads::<s::basic::antidote::Allocator<i64>>();
+    #[cfg(feature = "parking_lot")]
+    sent_across_threads::<s::basic::parking_lot::Allocator<i64>>();
+    #[cfg(feature = "simple_mutex")]
+    sent_across_threads::<s::basic::simple_mutex::Allocator<i64>>();
+    sent_across_threads::<s::basic::std::Allocator<i64>>();
+}
+
+#[test]
+fn s_advanced() {
+    keeps_allocator_alive::<s::advanced::v1::Allocator<i64>>();
+    keeps_allocator_alive::<s::advanced::v2::Allocator<i64>>();
+    keeps_allocator_alive::<s::advanced::v3::Allocator<i64>>();
+}
+
+#[test]
+fn u() {
+    keeps_allocator_alive::<u::v1::Allocator<i64>>();
+    keeps_allocator_alive::<u::v2::Allocator<i64>>();
+    keeps_allocator_alive::<u::v3::Allocator<i64>>();
+    keeps_allocator_alive::<u::v4::Allocator<i64>>();
+    sent_across_threads::<u::v1::Allocator<i64>>();
+    sent_across_threads::<u::v2::Allocator<i64>>();
+    sent_across_threads::<u::v3::Allocator<i64>>();
+    sent_across_threads::<u::v4::Allocator<i64>>();
+}
+
+#[test]
+fn module_aliases() {
+    let a = Arc::new(u::v1::Allocator::new(1));
+    let b: u::v1::OwnedBox<i64> = a.box_it_owned(123);
+    assert_eq!(123, *b);
+}
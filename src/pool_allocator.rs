@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests;
+
+use crate::error::OutOfMemory;
+use crate::owned::OwnedBox;
+use crate::wait::BoxItFuture;
+use crate::wait::Waiters;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Interface shared by every allocator variant in [`crate::s`] and
+/// [`crate::u`], so that callers can be written once and switch
+/// between implementations by changing a single type.
+pub trait PoolAllocator<T>: Sized {
+    type Box<'a>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self;
+
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Self::Box<'_>, OutOfMemory<T>>;
+
+    /// The queue notified by `Self::Box` whenever a slot is released.
+    fn waiters(&self) -> &Waiters;
+
+    #[track_caller]
+    fn box_it(&self, value: T) -> Self::Box<'_> {
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    /// Parks the calling thread until a slot is free.
+    fn box_it_wait(&self, value: T) -> Self::Box<'_> {
+        match self.waiters().wait(value, None, |v| self.try_box_it(v)) {
+            Ok(b) => b,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Like [`Self::box_it_wait`], but gives the value back once
+    /// `timeout` has elapsed without a slot becoming free.
+    fn box_it_timeout(
+        &self,
+        value: T,
+        timeout: Duration,
+    ) -> Result<Self::Box<'_>, OutOfMemory<T>> {
+        let deadline = Instant::now().checked_add(timeout);
+        self.waiters().wait(value, deadline, |v| self.try_box_it(v))
+    }
+
+    /// Resolves once a slot is free, without blocking the thread.
+    fn box_it_async(&self, value: T) -> BoxItFuture<'_, Self, T> {
+        BoxItFuture::new(self, value)
+    }
+
+    #[track_caller]
+    fn box_it_owned(self: &Arc<Self>, value: T) -> OwnedBox<Self, T>
+    where
+        Self: 'static,
+    {
+        self.try_box_it_owned(value)
+            .expect("out of reserved memory")
+    }
+
+    fn try_box_it_owned(
+        self: &Arc<Self>,
+        value: T,
+    ) -> Result<OwnedBox<Self, T>, OutOfMemory<T>>
+    where
+        Self: 'static,
+    {
+        OwnedBox::try_new(self, value)
+    }
+}
@@ -0,0 +1,111 @@
+use super::PoolAllocator;
+use crate::backoff::ExponentialSpin;
+use crate::backoff::Spin;
+use crate::backoff::SpinThenPark;
+use crate::backoff::SpinThenYield;
+use crate::s;
+use crate::u;
+use std::mem::drop;
+use std::time::Duration;
+
+fn equality<A: PoolAllocator<i64>>() {
+    let a = A::new(1);
+    let b = a.box_it(123);
+    assert_eq!(123, *b);
+}
+
+fn mutation<A: PoolAllocator<i64>>() {
+    let a = A::new(2);
+    let mut b = a.box_it(123);
+    let c = a.box_it(234);
+    *b += 1;
+    assert_eq!(124, *b);
+    assert_eq!(234, *c);
+}
+
+fn memory_reclamation<A: PoolAllocator<i64>>() {
+    let a = A::new(1);
+    let b = a.box_it(123);
+    drop(b);
+    let c = a.box_it(234);
+    assert_eq!(234, *c);
+}
+
+fn out_of_memory<A: PoolAllocator<i64>>() {
+    let a = A::new(1);
+    let b = a.box_it(123);
+    let error = a.try_box_it(234).err().unwrap();
+    assert_eq!("out of reserved memory", error.to_string());
+    assert_eq!(234, error.into_inner());
+    drop(b);
+    assert_eq!(345, *a.try_box_it(345).unwrap());
+}
+
+fn wait_for_release<A: PoolAllocator<i64> + Sync>() {
+    let a = A::new(1);
+    let b = a.box_it(123);
+
+    std::thread::scope(|s| {
+        let waiter = s.spawn(|| *a.box_it_wait(234));
+        std::thread::sleep(Duration::from_millis(10));
+        drop(b);
+        assert_eq!(234, waiter.join().unwrap());
+    });
+}
+
+fn timeout_when_out_of_memory<A: PoolAllocator<i64>>() {
+    let a = A::new(1);
+    let b = a.box_it(123);
+    let timeout = Duration::from_millis(10);
+    let error = a.box_it_timeout(234, timeout).err().unwrap();
+    assert_eq!(234, error.into_inner());
+    drop(b);
+    assert_eq!(345, *a.box_it_timeout(345, timeout).unwrap());
+}
+
+fn all<A: PoolAllocator<i64> + Sync>() {
+    equality::<A>();
+    mutation::<A>();
+    memory_reclamation::<A>();
+    out_of_memory::<A>();
+    wait_for_release::<A>();
+    timeout_when_out_of_memory::<A>();
+}
+
+#[test]
+fn s_basic() {
+    #[cfg(feature = "antidote")]
+    all::<s::basic::antidote::Allocator<i64>>();
+    #[cfg(feature = "parking_lot")]
+    all::<s::basic::parking_lot::Allocator<i64>>();
+    #[cfg(feature = "simple_mutex")]
+    all::<s::basic::simple_mutex::Allocator<i64>>();
+    all::<s::basic::std::Allocator<i64>>();
+}
+
+#[test]
+fn s_advanced() {
+    all::<s::advanced::v1::Allocator<i64>>();
+    all::<s::advanced::v2::Allocator<i64>>();
+    all::<s::advanced::v3::Allocator<i64>>();
+}
+
+#[test]
+fn u() {
+    all::<u::v1::Allocator<i64>>();
+    all::<u::v2::Allocator<i64>>();
+    all::<u::v3::Allocator<i64>>();
+    all::<u::v4::Allocator<i64>>();
+}
+
+#[test]
+fn backoff_strategies() {
+    all::<s::advanced::v3::Allocator<i64, Spin>>();
+    all::<s::advanced::v3::Allocator<i64, ExponentialSpin>>();
+    all::<s::advanced::v3::Allocator<i64, SpinThenYield>>();
+    all::<s::advanced::v3::Allocator<i64, SpinThenPark>>();
+    all::<u::v3::Allocator<i64, Spin>>();
+    all::<u::v3::Allocator<i64, ExponentialSpin>>();
+    all::<u::v3::Allocator<i64, SpinThenYield>>();
+    all::<u::v3::Allocator<i64, SpinThenPark>>();
+}
@@ -3,6 +3,10 @@
 #[cfg(test)]
 mod tests;
 
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use crate::wait::NotifyOnDrop;
+use crate::wait::Waiters;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::sync::Mutex;
@@ -30,11 +34,12 @@ impl<T> Slot<T> {
 pub struct Allocator<T> {
     storage: std::boxed::Box<[Slot<T>]>,
     free: Mutex<isize>,
+    waiters: Waiters,
 }
 
 impl<T> Allocator<T> {
     pub fn new(capacity: usize) -> Self {
-        assert!(1 <= capacity, capacity <= (isize::MAX as usize));
+        assert!(1 <= capacity && capacity <= (isize::MAX as usize));
         let mut storage = Vec::with_capacity(capacity);
 
         for next_free_slot_index in 1..capacity {
@@ -48,19 +53,43 @@ impl<T> Allocator<T> {
         Self {
             storage,
             free: Mutex::new(0),
+            waiters: Waiters::new(),
         }
     }
 
     #[track_caller]
     pub fn box_it(&self, value: T) -> Box<'_, T> {
-        let Self { storage, free } = &self;
-        let mut free_guard = free.lock().unwrap();
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        let Self {
+            storage,
+            free,
+            waiters,
+        } = &self;
+        let mut free_guard = match free.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
         let index = *free_guard;
 
-        assert_ne!(INVALID_INDEX, index, "out of reserved memory");
+        if INVALID_INDEX == index {
+            return Err(OutOfMemory(value));
+        }
 
-        let mut slot_guard =
-            storage[index as usize].inner.lock().unwrap();
+        // A panic in `T::drop` poisons the lock of its slot, which
+        // `Box::drop` releases last, but not before the slot is back on
+        // the free list.
+        let mut slot_guard = match storage[index as usize].inner.lock()
+        {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
 
         let next_free = match slot_guard.deref() {
             SlotInner::Empty(n) => *n,
@@ -71,18 +100,44 @@ impl<T> Allocator<T> {
         std::mem::drop(free_guard);
         *slot_guard = SlotInner::Filled(value);
 
-        Box {
+        Ok(Box {
             free,
             index,
             inner: slot_guard,
-        }
+            _notify: NotifyOnDrop(waiters),
+        })
+    }
+}
+
+impl<T> PoolAllocator<T> for Allocator<T> {
+    type Box<'a>
+        = Box<'a, T>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self {
+        Self::new(capacity)
+    }
+
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        self.try_box_it(value)
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.waiters
     }
 }
 
+pub type OwnedBox<T> = crate::owned::OwnedBox<Allocator<T>, T>;
+
 pub struct Box<'a, T> {
     inner: MutexGuard<'a, SlotInner<T>>,
     free: &'a Mutex<isize>,
     index: isize,
+    _notify: NotifyOnDrop<'a>,
 }
 
 impl<T> Deref for Box<'_, T> {
@@ -112,7 +167,13 @@ impl<T> Drop for Box<'_, T> {
             Err(poisoned) => poisoned.into_inner(),
         };
 
-        *self.inner = SlotInner::Empty(*free_guard);
+        let value = std::mem::replace(
+            &mut *self.inner,
+            SlotInner::Empty(*free_guard),
+        );
+
         *free_guard = self.index;
+        std::mem::drop(free_guard);
+        std::mem::drop(value);
     }
 }
@@ -1,13 +1,20 @@
-#![allow(unused_imports)]
-
 #[cfg(test)]
 mod tests;
 
+use crate::backoff::Backoff;
+use crate::backoff::SpinThenYield;
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use crate::sync::AtomicIsize;
+use crate::wait::NotifyOnDrop;
+use crate::wait::Waiters;
+use std::marker::PhantomData;
 use std::ops::Deref;
 use std::ops::DerefMut;
-use std::sync::atomic::Ordering::{AcqRel, Acquire, Release, SeqCst};
+use std::sync::atomic::Ordering::{AcqRel, Acquire};
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use std::sync::TryLockError;
 
 const INVALID_INDEX: isize = -1;
 
@@ -28,14 +35,24 @@ impl<T> Slot<T> {
     }
 }
 
-pub struct Allocator<T> {
+pub struct Allocator<T, B = SpinThenYield> {
     storage: std::boxed::Box<[Slot<T>]>,
-    free: std::sync::atomic::AtomicIsize,
+    free: AtomicIsize,
+    waiters: Waiters,
+    _backoff: PhantomData<fn() -> B>,
 }
 
 impl<T> Allocator<T> {
     pub fn new(capacity: usize) -> Self {
-        assert!(1 <= capacity, capacity <= (isize::MAX as usize));
+        Self::with_backoff(capacity)
+    }
+}
+
+impl<T, B: Backoff> Allocator<T, B> {
+    /// Like [`Allocator::new`], but retries contended operations
+    /// according to `B`.
+    pub fn with_backoff(capacity: usize) -> Self {
+        assert!(1 <= capacity && capacity <= (isize::MAX as usize));
         let mut storage = Vec::with_capacity(capacity);
 
         for next_free_slot_index in 1..capacity {
@@ -48,61 +65,107 @@ impl<T> Allocator<T> {
 
         Self {
             storage,
-            free: std::sync::atomic::AtomicIsize::new(0),
+            free: AtomicIsize::new(0),
+            waiters: Waiters::new(),
+            _backoff: PhantomData,
         }
     }
 
     #[track_caller]
     pub fn box_it(&self, value: T) -> Box<'_, T> {
-        let Self { storage, free } = &self;
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        let Self {
+            storage,
+            free,
+            waiters,
+            ..
+        } = &self;
+
+        let mut backoff = B::default();
 
         loop {
             let index = free.load(Acquire);
 
-            match storage
-                .get(index as usize)
-                .expect("out of reserved memory")
-                .inner
-                .try_lock()
-            {
-                Ok(mut guard) => {
-                    let next_free = match *guard {
-                        SlotInner::Empty(n) => n,
-                        SlotInner::Filled(_) => unreachable!(),
-                    };
-
-                    if free
-                        .compare_exchange_weak(
-                            index, next_free, AcqRel, Acquire,
-                        )
-                        .is_ok()
-                    {
-                        *guard = SlotInner::Filled(value);
-
-                        return Box {
-                            free,
-                            index,
-                            inner: guard,
-                        };
-                    }
-
-                    std::sync::atomic::spin_loop_hint();
+            let slot = match storage.get(index as usize) {
+                Some(slot) => slot,
+                None => return Err(OutOfMemory(value)),
+            };
+
+            let mut guard = match slot.inner.try_lock() {
+                Ok(guard) => guard,
+                // Poisoned by a panic in `T::drop`, after `Box::drop`
+                // had already emptied the slot.
+                Err(TryLockError::Poisoned(poisoned)) => {
+                    poisoned.into_inner()
                 }
-                Err(std::sync::TryLockError::WouldBlock) => {
-                    std::thread::yield_now();
-                }
-                Err(std::sync::TryLockError::Poisoned(e)) => {
-                    panic!("{}", e)
+                Err(TryLockError::WouldBlock) => {
+                    backoff.backoff();
+                    continue;
                 }
+            };
+
+            let next_free = match *guard {
+                SlotInner::Empty(n) => n,
+                SlotInner::Filled(_) => unreachable!(),
+            };
+
+            if free
+                .compare_exchange_weak(
+                    index, next_free, AcqRel, Acquire,
+                )
+                .is_ok()
+            {
+                *guard = SlotInner::Filled(value);
+
+                return Ok(Box {
+                    free,
+                    index,
+                    inner: guard,
+                    _notify: NotifyOnDrop(waiters),
+                });
             }
+
+            backoff.backoff();
         }
     }
 }
 
+impl<T, B: Backoff> PoolAllocator<T> for Allocator<T, B> {
+    type Box<'a>
+        = Box<'a, T>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self {
+        Self::with_backoff(capacity)
+    }
+
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        self.try_box_it(value)
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.waiters
+    }
+}
+
+pub type OwnedBox<T, B = SpinThenYield> =
+    crate::owned::OwnedBox<Allocator<T, B>, T>;
+
 pub struct Box<'a, T> {
     inner: MutexGuard<'a, SlotInner<T>>,
-    free: &'a std::sync::atomic::AtomicIsize,
+    free: &'a AtomicIsize,
     index: isize,
+    _notify: NotifyOnDrop<'a>,
 }
 
 impl<T> Deref for Box<'_, T> {
@@ -127,7 +190,11 @@ impl<T> DerefMut for Box<'_, T> {
 
 impl<T> Drop for Box<'_, T> {
     fn drop(&mut self) {
-        *self.inner =
-            SlotInner::Empty(self.free.swap(self.index, AcqRel));
+        let value = std::mem::replace(
+            &mut *self.inner,
+            SlotInner::Empty(self.free.swap(self.index, AcqRel)),
+        );
+
+        std::mem::drop(value);
     }
 }
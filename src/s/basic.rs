@@ -1,4 +1,126 @@
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "antidote")]
 pub mod antidote;
+#[cfg(feature = "parking_lot")]
 pub mod parking_lot;
+#[cfg(feature = "simple_mutex")]
 pub mod simple_mutex;
 pub mod std;
+
+use crate::align128::Align128;
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use crate::wait::NotifyOnDrop;
+use crate::wait::Waiters;
+use ::std::ops::Deref;
+use ::std::ops::DerefMut;
+use lock_api::Mutex;
+use lock_api::MutexGuard;
+use lock_api::RawMutex;
+
+// A `Box` keeps its slot locked for as long as it lives.
+type Slot<T, M> = Mutex<M, Option<T>>;
+
+/// An allocator whose slots are each protected by an `M`. The modules
+/// of this one provide adapters for several mutex implementations.
+pub struct Allocator<T, M> {
+    storage: ::std::boxed::Box<[Align128<Slot<T, M>>]>,
+    waiters: Waiters,
+}
+
+impl<T, M: RawMutex> Allocator<T, M> {
+    pub fn new(capacity: usize) -> Self {
+        let mut storage = Vec::with_capacity(capacity);
+        storage.resize_with(capacity, || Align128(Mutex::new(None)));
+
+        Self {
+            storage: storage.into_boxed_slice(),
+            waiters: Waiters::new(),
+        }
+    }
+
+    #[track_caller]
+    pub fn box_it(&self, value: T) -> Box<'_, T, M> {
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T, M>, OutOfMemory<T>> {
+        match self.storage.iter().find_map(|slot| slot.try_lock()) {
+            Some(mut guard) => {
+                *guard = Some(value);
+
+                Ok(Box {
+                    guard: Some(guard),
+                    _notify: NotifyOnDrop(&self.waiters),
+                })
+            }
+            None => Err(OutOfMemory(value)),
+        }
+    }
+}
+
+impl<T, M: RawMutex> PoolAllocator<T> for Allocator<T, M> {
+    type Box<'a>
+        = Box<'a, T, M>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self {
+        Self::new(capacity)
+    }
+
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T, M>, OutOfMemory<T>> {
+        self.try_box_it(value)
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.waiters
+    }
+}
+
+/// Keeps its slot locked for as long as it lives, so it is `Send` only
+/// if `M` may be unlocked from another thread.
+pub struct Box<'a, T, M: RawMutex> {
+    // Only `None` while dropping, so that the slot can be unlocked
+    // before the value is dropped.
+    guard: Option<MutexGuard<'a, M, Option<T>>>,
+    _notify: NotifyOnDrop<'a>,
+}
+
+impl<T, M: RawMutex> Deref for Box<'_, T, M> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self.guard.as_deref() {
+            Some(Some(value)) => value,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<T, M: RawMutex> DerefMut for Box<'_, T, M> {
+    fn deref_mut(&mut self) -> &mut T {
+        match self.guard.as_deref_mut() {
+            Some(Some(value)) => value,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<T, M: RawMutex> Drop for Box<'_, T, M> {
+    fn drop(&mut self) {
+        // The guard is gone, and the slot unlocked, by the time the
+        // closure returns the value.
+        let value =
+            self.guard.take().and_then(|mut guard| guard.take());
+        ::std::mem::drop(value);
+    }
+}
@@ -2,65 +2,45 @@
 mod tests;
 
 use antidote::Mutex;
-use antidote::MutexGuard;
-use std::ops::Deref;
-use std::ops::DerefMut;
+use lock_api::GuardSend;
 
-pub struct Allocator<T> {
-    storage: std::boxed::Box<[Mutex<Option<T>>]>,
-}
-
-impl<T> Allocator<T> {
-    pub fn new(capacity: usize) -> Self {
-        let mut storage = Vec::with_capacity(capacity);
-        storage
-            .resize_with(capacity, || Mutex::new(Default::default()));
-
-        Self {
-            storage: storage.into_boxed_slice(),
-        }
-    }
+pub type Allocator<T> = super::Allocator<T, RawMutex>;
 
-    #[track_caller]
-    pub fn box_it(&self, value: T) -> Box<'_, T> {
-        let mut guard = self
-            .storage
-            .iter()
-            .find_map(|mutex| mutex.try_lock().ok())
-            .expect("out of reserved memory");
+pub type OwnedBox<T> = crate::owned::OwnedBox<Allocator<T>, T>;
 
-        *guard = Some(value);
+pub type Box<'a, T> = super::Box<'a, T, RawMutex>;
 
-        Box { inner: guard }
-    }
+// Like `s::basic::std::RawMutex`, the mutex only guards a flag, so
+// that the slot can be unlocked from any thread.
+pub struct RawMutex {
+    locked: Mutex<bool>,
 }
 
-pub struct Box<'a, T> {
-    inner: MutexGuard<'a, Option<T>>,
-}
+unsafe impl lock_api::RawMutex for RawMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        locked: Mutex::new(false),
+    };
 
-impl<T> Deref for Box<'_, T> {
-    type Target = T;
+    type GuardMarker = GuardSend;
 
-    fn deref(&self) -> &T {
-        match self.inner.deref() {
-            Some(value) => value,
-            None => unreachable!(),
+    fn lock(&self) {
+        while !self.try_lock() {
+            std::thread::yield_now();
         }
     }
-}
 
-impl<T> DerefMut for Box<'_, T> {
-    fn deref_mut(&mut self) -> &mut T {
-        match self.inner.deref_mut() {
-            Some(value) => value,
-            None => unreachable!(),
+    fn try_lock(&self) -> bool {
+        match self.locked.try_lock() {
+            Ok(mut locked) if !*locked => {
+                *locked = true;
+                true
+            }
+            _ => false,
         }
     }
-}
 
-impl<T> Drop for Box<'_, T> {
-    fn drop(&mut self) {
-        *self.inner = None;
+    unsafe fn unlock(&self) {
+        *self.locked.lock() = false;
     }
 }
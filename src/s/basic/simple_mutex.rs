@@ -1,65 +1,54 @@
 #[cfg(test)]
 mod tests;
 
+use lock_api::GuardSend;
 use simple_mutex::Mutex;
-use simple_mutex::MutexGuard;
-use std::ops::Deref;
-use std::ops::DerefMut;
+use std::sync::OnceLock;
 
-pub struct Allocator<T> {
-    storage: std::boxed::Box<[Mutex<Option<T>>]>,
-}
-
-impl<T> Allocator<T> {
-    pub fn new(capacity: usize) -> Self {
-        let mut storage = Vec::with_capacity(capacity);
-        storage.resize_with(capacity, Default::default);
+pub type Allocator<T> = super::Allocator<T, RawMutex>;
 
-        Self {
-            storage: storage.into_boxed_slice(),
-        }
-    }
+pub type OwnedBox<T> = crate::owned::OwnedBox<Allocator<T>, T>;
 
-    #[track_caller]
-    pub fn box_it(&self, value: T) -> Box<'_, T> {
-        let mut guard = self
-            .storage
-            .iter()
-            .find_map(|mutex| mutex.try_lock())
-            .expect("out of reserved memory");
+pub type Box<'a, T> = super::Box<'a, T, RawMutex>;
 
-        *guard = Some(value);
+// Like `s::basic::std::RawMutex`, the mutex only guards a flag, so
+// that the slot can be unlocked from any thread. `simple_mutex` has no
+// `const` constructor, hence the lazy initialisation.
+pub struct RawMutex {
+    locked: OnceLock<Mutex<bool>>,
+}
 
-        Box { inner: guard }
+impl RawMutex {
+    fn locked(&self) -> &Mutex<bool> {
+        self.locked.get_or_init(|| Mutex::new(false))
     }
 }
 
-pub struct Box<'a, T> {
-    inner: MutexGuard<'a, Option<T>>,
-}
+unsafe impl lock_api::RawMutex for RawMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        locked: OnceLock::new(),
+    };
 
-impl<T> Deref for Box<'_, T> {
-    type Target = T;
+    type GuardMarker = GuardSend;
 
-    fn deref(&self) -> &T {
-        match self.inner.deref() {
-            Some(value) => value,
-            None => unreachable!(),
+    fn lock(&self) {
+        while !self.try_lock() {
+            std::thread::yield_now();
         }
     }
-}
 
-impl<T> DerefMut for Box<'_, T> {
-    fn deref_mut(&mut self) -> &mut T {
-        match self.inner.deref_mut() {
-            Some(value) => value,
-            None => unreachable!(),
+    fn try_lock(&self) -> bool {
+        match self.locked().try_lock() {
+            Some(mut locked) if !*locked => {
+                *locked = true;
+                true
+            }
+            _ => false,
         }
     }
-}
 
-impl<T> Drop for Box<'_, T> {
-    fn drop(&mut self) {
-        *self.inner = None;
+    unsafe fn unlock(&self) {
+        *self.locked().lock() = false;
     }
 }
@@ -0,0 +1,56 @@
+use lock_api::GuardNoSend;
+use lock_api::RawMutex;
+use std::mem::drop;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+// The smallest possible adapter, as a new backend would add it.
+struct SpinLock(AtomicBool);
+
+unsafe impl RawMutex for SpinLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self(AtomicBool::new(false));
+
+    type GuardMarker = GuardNoSend;
+
+    fn lock(&self) {
+        while !self.try_lock() {
+            std::hint::spin_loop();
+        }
+    }
+
+    fn try_lock(&self) -> bool {
+        self.0
+            .compare_exchange(false, true, Acquire, Relaxed)
+            .is_ok()
+    }
+
+    unsafe fn unlock(&self) {
+        self.0.store(false, Release);
+    }
+}
+
+crate::conformance::suite!(
+    super::Allocator::<_, SpinLock>::new,
+    InOrder
+);
+
+#[test]
+fn custom_raw_mutex() {
+    let a = super::Allocator::<i64, SpinLock>::new(1);
+    let mut b = a.box_it(123);
+    *b += 1;
+    assert_eq!(124, *b);
+    assert_eq!(234, a.try_box_it(234).err().unwrap().into_inner());
+    drop(b);
+    assert_eq!(345, *a.box_it(345));
+}
+
+#[test]
+fn drops_value_once_unlocked() {
+    let a = super::Allocator::<std::sync::Arc<()>, SpinLock>::new(1);
+    let value = std::sync::Arc::new(());
+    drop(a.box_it(value.clone()));
+    assert_eq!(1, std::sync::Arc::strong_count(&value));
+    assert!(a.storage.iter().all(|slot| !slot.is_locked()));
+}
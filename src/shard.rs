@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests;
+
+use std::cell::RefCell;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::sync::Weak;
+
+/// Spreads threads over a fixed number of shards, so that allocators
+/// can start their search for a free slot at a different place for
+/// every thread.
+///
+/// Threads are assigned the least loaded shard, either explicitly via
+/// [`Shards::register`] or implicitly on their first call to
+/// [`Shards::current`]. Implicit registrations end when the thread
+/// exits, after which the remaining threads move off overloaded shards
+/// the next time they ask for their shard.
+#[derive(Debug)]
+pub struct Shards {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    counts: std::boxed::Box<[AtomicUsize]>,
+    exits: AtomicUsize,
+}
+
+struct Entry {
+    inner: Weak<Inner>,
+    shard: usize,
+    exits: usize,
+}
+
+thread_local! {
+    static ENTRIES: RefCell<Vec<Entry>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+impl Shards {
+    pub fn new(count: usize) -> Self {
+        assert!(1 <= count);
+        let mut counts = Vec::with_capacity(count);
+        counts.resize_with(count, Default::default);
+
+        Self {
+            inner: Arc::new(Inner {
+                counts: counts.into_boxed_slice(),
+                exits: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.inner.counts.len()
+    }
+
+    /// Assigns a shard for as long as the returned handle lives.
+    pub fn register(&self) -> Registration<'_> {
+        Registration {
+            shards: self,
+            shard: self.inner.join(),
+        }
+    }
+
+    /// The shard of the calling thread, which is registered on first
+    /// use.
+    pub fn current(&self) -> usize {
+        ENTRIES
+            .try_with(|entries| {
+                let mut entries = entries.borrow_mut();
+                let this = Arc::as_ptr(&self.inner);
+
+                match entries
+                    .iter_mut()
+                    .find(|e| e.inner.as_ptr() == this)
+                {
+                    Some(entry) => {
+                        self.inner.rebalance(entry);
+                        entry.shard
+                    }
+                    None => {
+                        entries.retain(|e| e.inner.strong_count() != 0);
+                        let exits = self.inner.exits.load(Relaxed);
+                        let shard = self.inner.join();
+
+                        entries.push(Entry {
+                            inner: Arc::downgrade(&self.inner),
+                            shard,
+                            exits,
+                        });
+
+                        shard
+                    }
+                }
+            })
+            // The thread is exiting and its entries are gone.
+            .unwrap_or(0)
+    }
+}
+
+impl Inner {
+    fn join(&self) -> usize {
+        loop {
+            let (shard, count) = self
+                .counts
+                .iter()
+                .map(|count| count.load(Relaxed))
+                .enumerate()
+                .min_by_key(|&(_, count)| count)
+                .unwrap();
+
+            // Fails if another thread has joined the same shard since,
+            // which may no longer be the least loaded one then.
+            if self.counts[shard]
+                .compare_exchange(count, count + 1, Relaxed, Relaxed)
+                .is_ok()
+            {
+                return shard;
+            }
+        }
+    }
+
+    fn leave(&self, shard: usize) {
+        self.counts[shard].fetch_sub(1, Relaxed);
+        self.exits.fetch_add(1, Relaxed);
+    }
+
+    fn rebalance(&self, entry: &mut Entry) {
+        let exits = self.exits.load(Relaxed);
+
+        if exits == entry.exits {
+            return;
+        }
+
+        entry.exits = exits;
+        let count = self.counts[entry.shard].load(Relaxed);
+        let min = self.counts.iter().map(|c| c.load(Relaxed)).min();
+
+        if min.unwrap_or(count) + 1 < count {
+            self.counts[entry.shard].fetch_sub(1, Relaxed);
+            entry.shard = self.join();
+        }
+    }
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade() {
+            inner.leave(self.shard);
+        }
+    }
+}
+
+pub struct Registration<'shards> {
+    shards: &'shards Shards,
+    shard: usize,
+}
+
+impl Registration<'_> {
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.shards.inner.leave(self.shard);
+    }
+}
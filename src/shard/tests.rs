@@ -0,0 +1,74 @@
+use std::mem::drop;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Barrier;
+use std::thread;
+
+fn counts(shards: &super::Shards) -> Vec<usize> {
+    shards
+        .inner
+        .counts
+        .iter()
+        .map(|c| c.load(Relaxed))
+        .collect()
+}
+
+#[test]
+fn registrations_spread_evenly() {
+    let shards = super::Shards::new(3);
+    let registrations: Vec<_> =
+        (0..7).map(|_| shards.register()).collect();
+    assert_eq!(vec![3, 2, 2], counts(&shards));
+    drop(registrations);
+    assert_eq!(vec![0, 0, 0], counts(&shards));
+}
+
+#[test]
+fn no_overflow_past_u16() {
+    let shards = super::Shards::new(1);
+    let registrations: Vec<_> =
+        (0..70_000).map(|_| shards.register()).collect();
+    assert_eq!(vec![70_000], counts(&shards));
+    drop(registrations);
+    assert_eq!(vec![0], counts(&shards));
+}
+
+#[test]
+fn current_is_stable_per_thread() {
+    let shards = super::Shards::new(4);
+    let shard = shards.current();
+    assert_eq!(shard, shards.current());
+    assert_eq!(1, counts(&shards).iter().sum::<usize>());
+}
+
+#[test]
+fn threads_unregister_on_exit() {
+    let shards = super::Shards::new(4);
+    let barrier = Barrier::new(4);
+
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                shards.current();
+                barrier.wait();
+                assert_eq!(vec![1, 1, 1, 1], counts(&shards));
+                barrier.wait();
+            });
+        }
+    });
+
+    assert_eq!(vec![0, 0, 0, 0], counts(&shards));
+}
+
+#[test]
+fn rebalances_after_exits() {
+    let shards = super::Shards::new(2);
+    assert_eq!(0, shards.current());
+    let (zero, one): (Vec<_>, Vec<_>) = (0..4)
+        .map(|_| shards.register())
+        .partition(|r| r.shard() == 0);
+    assert_eq!(vec![3, 2], counts(&shards));
+    drop(one);
+    assert_eq!(1, shards.current());
+    assert_eq!(vec![2, 1], counts(&shards));
+    drop(zero);
+}
@@ -1,3 +1,9 @@
+#[cfg(feature = "std")]
+mod segments;
+#[cfg(feature = "std")]
 pub mod v1;
+#[cfg(feature = "std")]
 pub mod v2;
+pub mod v3;
+#[cfg(feature = "std")]
 pub mod v4;
@@ -0,0 +1,127 @@
+use crate::sync::AtomicUsize;
+use crate::sync::Mutex;
+use std::ops::Range;
+use std::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+
+pub(crate) enum Growth {
+    Grown,
+    Raced,
+    Exhausted,
+}
+
+/// Slot storage made of fixed-size segments that are allocated on
+/// demand. Segments are never moved or freed before the whole storage
+/// is dropped, so references into them stay valid while it grows.
+#[derive(Debug)]
+pub(crate) struct Segments<S> {
+    segment_len: usize,
+    max_len: usize,
+    segments: std::boxed::Box<[OnceLock<std::boxed::Box<[S]>>]>,
+    len: AtomicUsize,
+    grow: Mutex<()>,
+}
+
+impl<S> Segments<S> {
+    pub(crate) fn new(
+        segment_len: usize,
+        max_len: usize,
+        segment: impl FnOnce(Range<usize>) -> std::boxed::Box<[S]>,
+    ) -> Self {
+        assert!(1 <= segment_len && segment_len <= max_len);
+        let count = (max_len - 1) / segment_len + 1;
+        let mut segments = Vec::with_capacity(count);
+        segments.resize_with(count, OnceLock::new);
+
+        let first = segment(0..segment_len);
+        debug_assert!(segment_len == first.len());
+        let _ = segments[0].set(first);
+
+        Self {
+            segment_len,
+            max_len,
+            segments: segments.into_boxed_slice(),
+            len: AtomicUsize::new(segment_len),
+            grow: Mutex::new(()),
+        }
+    }
+
+    /// The number of slots allocated so far.
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Acquire)
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&S> {
+        self.segments
+            .get(index / self.segment_len)?
+            .get()?
+            .get(index % self.segment_len)
+    }
+
+    pub(crate) unsafe fn get_unchecked(&self, index: usize) -> &S {
+        self.segments
+            .get_unchecked(index / self.segment_len)
+            .get()
+            .unwrap_unchecked()
+            .get_unchecked(index % self.segment_len)
+    }
+
+    /// Maps a pointer to one of the slots back to its index, looking
+    /// at the segments allocated so far only.
+    #[cfg(feature = "nightly")]
+    pub(crate) fn index_of(&self, slot: *const S) -> Option<usize> {
+        let size = std::mem::size_of::<S>();
+        let address = slot as usize;
+        let allocated = self.len().div_ceil(self.segment_len);
+        let segments = &self.segments[..allocated];
+
+        segments.iter().enumerate().find_map(|(n, segment)| {
+            let segment = segment.get()?;
+            let offset =
+                address.checked_sub(segment.as_ptr() as usize)?;
+
+            if offset < segment.len() * size {
+                debug_assert!(offset % size == 0);
+                Some(n * self.segment_len + offset / size)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Allocates the next segment, unless the storage no longer has
+    /// `seen_len` slots, i.e. another thread has already grown it.
+    ///
+    /// `link` is called with the indices of the new slots before
+    /// [`Self::len`] is updated, so a thread that observes the new
+    /// length also observes whatever `link` did with them.
+    pub(crate) fn grow(
+        &self,
+        seen_len: usize,
+        segment: impl FnOnce(Range<usize>) -> std::boxed::Box<[S]>,
+        link: impl FnOnce(Range<usize>),
+    ) -> Growth {
+        let _guard =
+            self.grow.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let len = self.len();
+
+        if len != seen_len {
+            return Growth::Raced;
+        }
+
+        if len == self.max_len {
+            return Growth::Exhausted;
+        }
+
+        let range = len..(len + self.segment_len).min(self.max_len);
+        let new = segment(range.clone());
+        debug_assert!(range.len() == new.len());
+        let _ = self.segments[len / self.segment_len].set(new);
+        let end = range.end;
+        link(range);
+        self.len.store(end, Release);
+        Growth::Grown
+    }
+}
@@ -3,10 +3,17 @@
 #[cfg(test)]
 mod tests;
 
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use crate::u::segments::Growth;
+use crate::u::segments::Segments;
+use crate::wait::Waiters;
 use std::cell::UnsafeCell;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::ops::Range;
 use std::sync::Mutex;
+use std::sync::PoisonError;
 
 const INVALID_INDEX: isize = -1;
 
@@ -30,42 +37,78 @@ impl<T> Slot<T> {
             )),
         }
     }
+
+    fn segment(range: Range<usize>) -> std::boxed::Box<[Self]> {
+        let mut segment = Vec::with_capacity(range.len());
+
+        for next_free_slot_index in range.start + 1..range.end {
+            segment.push(Self::new(next_free_slot_index as isize))
+        }
+
+        segment.push(Self::new(INVALID_INDEX));
+        segment.into_boxed_slice()
+    }
 }
 
 pub struct Allocator<T> {
-    storage: std::boxed::Box<[Slot<T>]>,
+    storage: Segments<Slot<T>>,
     free: Mutex<isize>,
+    waiters: Waiters,
 }
 
 impl<T> Allocator<T> {
     pub fn new(capacity: usize) -> Self {
-        assert!(1 <= capacity, capacity <= (isize::MAX as usize));
-        let mut storage = Vec::with_capacity(capacity);
-
-        for next_free_slot_index in 1..capacity {
-            storage.push(Slot::new(next_free_slot_index as isize))
-        }
+        Self::growable(capacity, capacity)
+    }
 
-        storage.push(Slot::new(INVALID_INDEX));
-        let storage = storage.into_boxed_slice();
-        debug_assert!(capacity == storage.len());
+    /// Starts out with `capacity` slots and adds segments of `capacity`
+    /// more whenever all of them are in use, up to `max_capacity`.
+    pub fn growable(capacity: usize, max_capacity: usize) -> Self {
+        assert!(1 <= capacity && capacity <= max_capacity);
+        assert!(max_capacity <= (isize::MAX as usize));
 
         Self {
-            storage,
+            storage: Segments::new(
+                capacity,
+                max_capacity,
+                Slot::segment,
+            ),
             free: Mutex::new(0),
+            waiters: Waiters::new(),
         }
     }
 
     #[track_caller]
     pub fn box_it(&self, value: T) -> Box<'_, T> {
-        let Self { storage, free } = &self;
-        let mut free_guard = free.lock().unwrap();
-        let index = *free_guard;
+        self.try_box_it(value).expect("out of reserved memory")
+    }
 
-        assert_ne!(INVALID_INDEX, index, "out of reserved memory");
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        let Self { storage, free, .. } = &self;
+        let mut free_guard =
+            free.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if INVALID_INDEX == *free_guard {
+            let link = |range: Range<usize>| {
+                *free_guard = range.start as isize;
+            };
+
+            match storage.grow(storage.len(), Slot::segment, link) {
+                Growth::Grown => {}
+                Growth::Raced | Growth::Exhausted => {
+                    return Err(OutOfMemory(value));
+                }
+            }
+        }
+
+        let index = *free_guard;
 
-        let slot_inner =
-            unsafe { &mut *storage[index as usize].inner.get() };
+        let slot_inner = unsafe {
+            &mut *storage.get_unchecked(index as usize).inner.get()
+        };
 
         let next_free = match slot_inner {
             SlotInner::Empty(n) => *n,
@@ -76,13 +119,37 @@ impl<T> Allocator<T> {
         std::mem::drop(free_guard);
         *slot_inner = SlotInner::Filled(value);
 
-        Box {
+        Ok(Box {
             allocator: self,
             index,
-        }
+        })
     }
 }
 
+impl<T> PoolAllocator<T> for Allocator<T> {
+    type Box<'a>
+        = Box<'a, T>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self {
+        Self::new(capacity)
+    }
+
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        self.try_box_it(value)
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.waiters
+    }
+}
+
+pub type OwnedBox<T> = crate::owned::OwnedBox<Allocator<T>, T>;
+
 pub struct Box<'a, T> {
     allocator: &'a Allocator<T>,
     index: isize,
@@ -132,8 +199,16 @@ impl<T> Drop for Box<'_, T> {
             Err(poisoned) => poisoned.into_inner(),
         };
 
-        *unsafe { self.slot_inner_mut() } =
-            SlotInner::Empty(*free_guard);
+        // The slot is released before the value is dropped, so that a
+        // panic in `T::drop` can neither leak it nor poison `free`.
+        let value = std::mem::replace(
+            unsafe { self.slot_inner_mut() },
+            SlotInner::Empty(*free_guard),
+        );
+
         *free_guard = self.index;
+        std::mem::drop(free_guard);
+        self.allocator.waiters.notify();
+        std::mem::drop(value);
     }
 }
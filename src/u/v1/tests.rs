@@ -1,26 +1,18 @@
-use std::mem::drop;
+crate::conformance::suite!(
+    super::Allocator::new,
+    Lifo,
+    Growable(super::Allocator::growable)
+);
 
 #[test]
-fn equality() {
+fn poisoned_free_list_still_allocates() {
     let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    assert_eq!(123, *b);
-}
-
-#[test]
-#[should_panic]
-fn panic_when_out_of_memory() {
-    let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    let c = a.box_it(234);
-    drop((b, c));
-}
-
-#[test]
-fn memory_reclamation() {
-    let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    drop(b);
-    let c = a.box_it(234);
-    assert_eq!(234, *c);
+    let result =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = a.free.lock();
+            panic!("boom");
+        }));
+    assert!(result.is_err());
+    assert!(a.free.is_poisoned());
+    assert_eq!(123, *a.box_it(123));
 }
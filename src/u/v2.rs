@@ -3,9 +3,15 @@
 #[cfg(test)]
 mod tests;
 
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use crate::u::segments::Growth;
+use crate::u::segments::Segments;
+use crate::wait::Waiters;
 use std::cell::UnsafeCell;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::ops::Range;
 use std::sync::Mutex;
 
 const INVALID_INDEX: isize = -1;
@@ -30,44 +36,77 @@ impl<T> Slot<T> {
             }),
         }
     }
+
+    fn segment(range: Range<usize>) -> std::boxed::Box<[Self]> {
+        let mut segment = Vec::with_capacity(range.len());
+
+        for next_free_slot_index in range.start + 1..range.end {
+            segment.push(Self::empty(next_free_slot_index as isize))
+        }
+
+        segment.push(Self::empty(INVALID_INDEX));
+        segment.into_boxed_slice()
+    }
 }
 
 pub struct Allocator<T> {
-    storage: std::boxed::Box<[Slot<T>]>,
+    storage: Segments<Slot<T>>,
     free: Mutex<isize>,
+    waiters: Waiters,
 }
 
 impl<T> Allocator<T> {
     pub fn new(capacity: usize) -> Self {
-        assert!(1 <= capacity, capacity <= (isize::MAX as usize));
-        let mut storage = Vec::with_capacity(capacity);
-
-        for next_free_slot_index in 1..capacity {
-            storage.push(Slot::empty(next_free_slot_index as isize))
-        }
+        Self::growable(capacity, capacity)
+    }
 
-        storage.push(Slot::empty(INVALID_INDEX));
-        let storage = storage.into_boxed_slice();
-        debug_assert!(capacity == storage.len());
+    /// Starts out with `capacity` slots and adds segments of `capacity`
+    /// more whenever all of them are in use, up to `max_capacity`.
+    pub fn growable(capacity: usize, max_capacity: usize) -> Self {
+        assert!(1 <= capacity && capacity <= max_capacity);
+        assert!(max_capacity <= (isize::MAX as usize));
 
         Self {
-            storage,
+            storage: Segments::new(
+                capacity,
+                max_capacity,
+                Slot::segment,
+            ),
             free: Mutex::new(0),
+            waiters: Waiters::new(),
         }
     }
 
     #[track_caller]
     pub fn box_it(&self, value: T) -> Box<'_, T> {
-        let Self { storage, free } = &self;
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        let Self { storage, free, .. } = &self;
 
         let mut free_guard = match free.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
 
-        let index = *free_guard;
+        if INVALID_INDEX == *free_guard {
+            let link = |range: Range<usize>| {
+                *free_guard = range.start as isize;
+            };
+
+            match storage.grow(storage.len(), Slot::segment, link) {
+                Growth::Grown => {}
+                Growth::Raced | Growth::Exhausted => {
+                    return Err(OutOfMemory(value));
+                }
+            }
+        }
 
-        assert_ne!(INVALID_INDEX, index, "out of reserved memory");
+        let index = *free_guard;
 
         let slot_inner =
             unsafe { storage.get_unchecked(index as usize) };
@@ -79,13 +118,37 @@ impl<T> Allocator<T> {
             filled: std::mem::ManuallyDrop::new(value),
         };
 
-        Box {
+        Ok(Box {
             allocator: self,
             index,
-        }
+        })
+    }
+}
+
+impl<T> PoolAllocator<T> for Allocator<T> {
+    type Box<'a>
+        = Box<'a, T>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self {
+        Self::new(capacity)
+    }
+
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        self.try_box_it(value)
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.waiters
     }
 }
 
+pub type OwnedBox<T> = crate::owned::OwnedBox<Allocator<T>, T>;
+
 pub struct Box<'a, T> {
     allocator: &'a Allocator<T>,
     index: isize,
@@ -129,8 +192,14 @@ impl<T> Drop for Box<'_, T> {
             Err(poisoned) => poisoned.into_inner(),
         };
 
-        *unsafe { self.slot_inner_mut() } =
-            SlotInner { empty: *free_guard };
+        let slot_inner = unsafe { self.slot_inner_mut() };
+        let value = unsafe {
+            std::mem::ManuallyDrop::take(&mut slot_inner.filled)
+        };
+        *slot_inner = SlotInner { empty: *free_guard };
         *free_guard = self.index;
+        std::mem::drop(free_guard);
+        self.allocator.waiters.notify();
+        std::mem::drop(value);
     }
 }
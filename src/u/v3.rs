@@ -1,159 +1,794 @@
-#![allow(unused_imports)]
-
-#[cfg(test)]
+#[cfg(all(feature = "std", not(loom)))]
+pub mod global;
+#[cfg(not(loom))]
+pub mod inline;
+#[cfg(feature = "std")]
+mod magazine;
+#[cfg(feature = "nightly")]
+pub mod pool;
+pub mod slice;
+#[cfg(all(test, feature = "std"))]
 mod tests;
 
 use crate::align128::Align128;
-use std::cell::UnsafeCell;
-use std::fmt::Debug;
-use std::mem::MaybeUninit;
+use crate::backoff::Backoff;
+#[cfg(feature = "std")]
+use crate::backoff::SpinThenYield;
+#[cfg(feature = "std")]
+use crate::error::OutOfMemory;
+#[cfg(feature = "std")]
+use crate::pool_allocator::PoolAllocator;
+#[cfg(feature = "std")]
+use crate::shard::Shards;
+use crate::sync::AtomicU32;
+#[cfg(target_has_atomic = "64")]
+use crate::sync::AtomicU64;
+use crate::sync::UnsafeCell;
+#[cfg(feature = "std")]
+use crate::u::segments::Growth;
+#[cfg(feature = "std")]
+use crate::u::segments::Segments;
+#[cfg(feature = "std")]
+use crate::wait::Waiters;
+use core::mem::MaybeUninit;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+#[cfg(feature = "std")]
+use magazine::Magazine;
+#[cfg(feature = "std")]
+use magazine::Magazines;
+#[cfg(feature = "std")]
+use std::marker::PhantomData;
+#[cfg(feature = "std")]
 use std::ops::Deref;
+#[cfg(feature = "std")]
 use std::ops::DerefMut;
-use std::sync::atomic::AtomicIsize;
-use std::sync::atomic::Ordering::{AcqRel, Acquire, Release, SeqCst};
+#[cfg(feature = "std")]
+use std::ops::Range;
+#[cfg(feature = "std")]
+use std::rc::Rc;
+
+// Targets without 64-bit atomics pack 16-bit tags and indices instead,
+// which limits their allocators to 65534 slots.
+#[cfg(target_has_atomic = "64")]
+type AtomicHead = AtomicU64;
+#[cfg(not(target_has_atomic = "64"))]
+type AtomicHead = AtomicU32;
+#[cfg(target_has_atomic = "64")]
+type HeadWord = u64;
+#[cfg(not(target_has_atomic = "64"))]
+type HeadWord = u32;
+
+const INDEX_BITS: u32 = HeadWord::BITS / 2;
+
+// The casts to `u32` here and in `Head` are no-ops where `HeadWord` is
+// `u32`.
+#[allow(clippy::unnecessary_cast)]
+const INVALID_INDEX: u32 = (HeadWord::MAX >> INDEX_BITS) as u32;
 
-const INVALID_INDEX: isize = isize::MAX;
+#[cfg(feature = "std")]
+const MAGAZINE_SIZE: usize = 32;
 
+// The head of the free list packs a slot index together with a tag
+// that is bumped by every successful push and pop. A thread that
+// stalls between reading `slot.next` and its CAS therefore fails the
+// CAS even if the same index has been popped and pushed back in the
+// meantime, which rules out the ABA interleaving traced in
+// `analysis.txt`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Head {
+    tag: u32,
+    index: u32,
+}
+
+#[allow(clippy::unnecessary_cast)]
+impl Head {
+    const fn pack(self) -> HeadWord {
+        ((self.tag as HeadWord) << INDEX_BITS) | self.index as HeadWord
+    }
+
+    fn unpack(word: HeadWord) -> Self {
+        Self {
+            tag: (word >> INDEX_BITS) as u32,
+            index: (word & INVALID_INDEX as HeadWord) as u32,
+        }
+    }
+
+    fn with_index(self, index: u32) -> Self {
+        Self {
+            tag: self.tag.wrapping_add(1),
+            index,
+        }
+    }
+}
+
+/// Identifies a value stored with [`Allocator::insert`]. The slot's
+/// generation is odd while it holds such a value and is bumped again
+/// when the value is removed, so a stale key never matches a reused
+/// slot.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    index: u32,
+    generation: u32,
+}
+
+/// The storage of a single value, public so that storage for
+/// [`slice::Allocator`] can be declared outside of this crate.
 #[derive(Debug)]
-struct Slot<T> {
-    next: Align128<AtomicIsize>,
+pub struct Slot<T, G = ()> {
+    next: Align128<AtomicU32>,
+    // Only kept by the `std` allocator, for its handle API.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    generation: G,
     data: UnsafeCell<MaybeUninit<T>>,
 }
 
-impl<T> Slot<T> {
-    fn empty(next: isize) -> Self {
+/// The slots of the `std` allocator.
+#[cfg(feature = "std")]
+type KeyedSlot<T> = Slot<T, AtomicU32>;
+
+// `data` is only accessed by whoever popped the slot off the free
+// list, much like a `Mutex<T>`.
+unsafe impl<T: Send, G: Sync> Sync for Slot<T, G> {}
+
+impl<T, G> Slot<T, G> {
+    #[cfg(not(loom))]
+    const fn with_generation(next: u32, generation: G) -> Self {
+        Self {
+            next: Align128(AtomicU32::new(next)),
+            generation,
+            data: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    // loom's atomics cannot be created in a `const fn`, which only
+    // `inline` needs anyway.
+    #[cfg(loom)]
+    fn with_generation(next: u32, generation: G) -> Self {
         Self {
-            next: Align128(AtomicIsize::new(next)),
+            next: Align128(AtomicU32::new(next)),
+            generation,
             data: UnsafeCell::new(MaybeUninit::uninit()),
         }
     }
 }
 
+impl<T> Slot<T> {
+    #[cfg(not(loom))]
+    const fn empty(next: u32) -> Self {
+        Self::with_generation(next, ())
+    }
+
+    #[cfg(loom)]
+    fn empty(next: u32) -> Self {
+        Self::with_generation(next, ())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> KeyedSlot<T> {
+    fn segment(range: Range<usize>) -> std::boxed::Box<[Self]> {
+        let mut segment = Vec::with_capacity(range.len());
+        let empty =
+            |next| Self::with_generation(next, AtomicU32::new(0));
+
+        for next in range.start + 1..range.end {
+            segment.push(empty(next as u32));
+        }
+
+        segment.push(empty(INVALID_INDEX));
+        segment.into_boxed_slice()
+    }
+}
+
+/// Pops the first slot off the free list rooted at `free`. `next`
+/// returns the link of a slot, or `None` for an invalid index.
+fn pop_slot<'a, B: Backoff>(
+    free: &AtomicHead,
+    next: impl Fn(u32) -> Option<&'a AtomicU32>,
+) -> Option<u32> {
+    let mut backoff = B::default();
+    let mut head = Head::unpack(free.load(Acquire));
+
+    loop {
+        // May be stale if another thread pops `head` first; the tag
+        // makes the CAS below fail in that case.
+        let next = next(head.index)?.load(Relaxed);
+
+        match free.compare_exchange_weak(
+            head.pack(),
+            head.with_index(next).pack(),
+            Acquire,
+            Acquire,
+        ) {
+            Ok(_) => return Some(head.index),
+            Err(new_head) => {
+                backoff.backoff();
+                head = Head::unpack(new_head);
+            }
+        }
+    }
+}
+
+/// Pushes a chain of slots, which must already be linked, from
+/// `first` to the slot whose link is `last`.
+fn push_chain<B: Backoff>(
+    free: &AtomicHead,
+    first: u32,
+    last: &AtomicU32,
+) {
+    let mut backoff = B::default();
+    let mut head = Head::unpack(free.load(Relaxed));
+
+    loop {
+        last.store(head.index, Relaxed);
+
+        match free.compare_exchange_weak(
+            head.pack(),
+            head.with_index(first).pack(),
+            Release,
+            Relaxed,
+        ) {
+            Ok(_) => break,
+            Err(new_head) => {
+                backoff.backoff();
+                head = Head::unpack(new_head);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 #[derive(Debug)]
-pub struct Allocator<T> {
-    storage: std::boxed::Box<[Slot<T>]>,
-    free: AtomicIsize,
+pub struct Allocator<T, B = SpinThenYield> {
+    storage: Segments<KeyedSlot<T>>,
+    // One free list per shard.
+    free: std::boxed::Box<[Align128<AtomicHead>]>,
+    shards: Shards,
+    waiters: Waiters,
+    magazines: Magazines,
+    _backoff: PhantomData<fn() -> B>,
 }
 
-unsafe impl<T: Debug> Sync for Allocator<T> {}
+#[cfg(feature = "std")]
+// `T: Send` like the slots, because `get` asks for `T: Sync` itself and
+// the boxes have the auto traits of `&mut T`.
+unsafe impl<T: Send, B> Sync for Allocator<T, B> {}
 
-impl<T: Debug> Allocator<T> {
+#[cfg(feature = "std")]
+impl<T> Allocator<T> {
     pub fn new(capacity: usize) -> Self {
-        assert!(1 <= capacity, capacity < (isize::MAX as usize));
-        let mut storage = Vec::with_capacity(capacity);
+        Self::growable(capacity, capacity)
+    }
 
-        for next in 1..capacity {
-            storage.push(Slot::empty(next as isize));
-        }
+    /// Starts out with `capacity` slots and adds segments of `capacity`
+    /// more whenever all of them are in use, up to `max_capacity`.
+    pub fn growable(capacity: usize, max_capacity: usize) -> Self {
+        Self::growable_with_backoff(capacity, max_capacity)
+    }
+
+    /// Like [`Self::growable`], but keeps a free list for each of
+    /// `shards` groups of threads instead of one for all of them.
+    /// Threads only take slots from the lists of other shards once
+    /// their own is empty.
+    pub fn sharded(
+        capacity: usize,
+        max_capacity: usize,
+        shards: usize,
+    ) -> Self {
+        Self::sharded_with_backoff(capacity, max_capacity, shards)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, B: Backoff> Allocator<T, B> {
+    /// Like [`Allocator::new`], but retries contended operations
+    /// according to `B`.
+    pub fn with_backoff(capacity: usize) -> Self {
+        Self::growable_with_backoff(capacity, capacity)
+    }
+
+    pub fn growable_with_backoff(
+        capacity: usize,
+        max_capacity: usize,
+    ) -> Self {
+        Self::sharded_with_backoff(capacity, max_capacity, 1)
+    }
 
-        storage.push(Slot::empty(INVALID_INDEX));
-        let storage = storage.into_boxed_slice();
-        debug_assert!(capacity == storage.len());
+    pub fn sharded_with_backoff(
+        capacity: usize,
+        max_capacity: usize,
+        shards: usize,
+    ) -> Self {
+        assert!(1 <= capacity && capacity <= max_capacity);
+        assert!(max_capacity < (INVALID_INDEX as usize));
+        let shards = Shards::new(shards);
+        let storage =
+            Segments::new(capacity, max_capacity, KeyedSlot::segment);
+
+        // Cuts the chain of the first segment into one per shard.
+        let free = (0..shards.count())
+            .map(|shard| {
+                let start = shard * capacity / shards.count();
+                let end = (shard + 1) * capacity / shards.count();
+                let mut index = INVALID_INDEX;
+
+                if start < end {
+                    let last =
+                        unsafe { storage.get_unchecked(end - 1) };
+                    last.next.store(INVALID_INDEX, Relaxed);
+                    index = start as u32;
+                }
+
+                Align128(AtomicHead::new(Head { tag: 0, index }.pack()))
+            })
+            .collect();
 
         Self {
             storage,
-            free: AtomicIsize::new(0),
+            free,
+            shards,
+            waiters: Waiters::new(),
+            magazines: Magazines::new(),
+            _backoff: PhantomData,
         }
     }
 
     #[track_caller]
-    pub fn box_it(&self, value: T) -> Box<'_, T> {
-        let mut head = self.free.load(SeqCst);
+    pub fn box_it(&self, value: T) -> Box<'_, T, B> {
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T, B>, OutOfMemory<T>> {
+        match self.allocate() {
+            Some(index) => {
+                let slot = unsafe { self.storage.get_unchecked(index) };
+                slot.data
+                    .with_mut(|data| unsafe { (*data).write(value) });
+
+                Ok(Box {
+                    allocator: self,
+                    index,
+                    _marker: PhantomData,
+                })
+            }
+            None => Err(OutOfMemory(value)),
+        }
+    }
+
+    #[track_caller]
+    pub fn insert(&self, value: T) -> Key {
+        self.try_insert(value).expect("out of reserved memory")
+    }
+
+    pub fn try_insert(&self, value: T) -> Result<Key, OutOfMemory<T>> {
+        match self.allocate() {
+            Some(index) => {
+                let slot = unsafe { self.storage.get_unchecked(index) };
+                slot.data
+                    .with_mut(|data| unsafe { (*data).write(value) });
+                let generation = slot.generation.load(Relaxed) + 1;
+                slot.generation.store(generation, Release);
+
+                Ok(Key {
+                    index: index as u32,
+                    generation,
+                })
+            }
+            None => Err(OutOfMemory(value)),
+        }
+    }
+
+    pub fn get(&self, key: Key) -> Option<&T>
+    where
+        T: Sync,
+    {
+        let slot = self.storage.get(key.index as usize)?;
+
+        if key.generation == slot.generation.load(Acquire) {
+            Some(unsafe { self.get_ref_unchecked(key.index as usize) })
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        let slot = self.storage.get(key.index as usize)?;
+
+        if key.generation == slot.generation.load(Relaxed) {
+            Some(unsafe { self.get_mut_unchecked(key.index as usize) })
+        } else {
+            None
+        }
+    }
+
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        let index = key.index as usize;
+        let slot = self.storage.get(index)?;
+
+        if key.generation != slot.generation.load(Relaxed) {
+            return None;
+        }
+
+        let value = unsafe { self.take(index) };
+        slot.generation
+            .store(key.generation.wrapping_add(1), Relaxed);
+        unsafe { self.deallocate(index) };
+        self.waiters.notify();
+        Some(value)
+    }
+
+    /// Returns a handle to the batch of free slots that the calling
+    /// thread keeps, so its boxes rarely touch the shared free list.
+    ///
+    /// The slots are only available to that thread until it exits,
+    /// except that a slot released while another thread or task waits
+    /// in `box_it_wait`, `box_it_timeout` or `box_it_async` goes to
+    /// the waiter instead.
+    pub fn thread_cache(&self) -> ThreadCache<'_, T, B> {
+        ThreadCache {
+            allocator: self,
+            magazine: self.magazines.current(),
+        }
+    }
+
+    fn allocate(&self) -> Option<usize> {
+        let shard = self.shard();
+        let count = self.free.len();
 
         loop {
-            let slot = match self.storage.get(head as usize) {
-                Some(s) => s,
-                None => {
-                    dbg!(head, &self);
-                    panic!("out of reserved memory")
-                }
-            };
+            let seen_len = self.storage.len();
 
-            let next = slot.next.load(SeqCst);
+            if let Some(index) = (0..count)
+                .find_map(|offset| self.pop((shard + offset) % count))
+            {
+                return Some(index);
+            }
 
-			
+            // Slots cached by exited threads come back before the
+            // storage grows.
+            if self.reclaim(shard) {
+                continue;
+            }
 
-            match self
-                .free
-                .compare_exchange_weak(head, next, SeqCst, SeqCst)
+            let link = |range: Range<usize>| unsafe {
+                self.push(shard, range.start, range.end - 1)
+            };
+
+            match self.storage.grow(seen_len, KeyedSlot::segment, link)
             {
-                Ok(head) => {
-                    // for debugging
-                    slot.next.store(-2, SeqCst);
-                    unsafe { &mut *slot.data.get() }.write(value);
-
-                    return Box {
-                        allocator: self,
-                        index: head as usize,
-                    };
+                Growth::Grown | Growth::Raced => {}
+                Growth::Exhausted => return None,
+            }
+        }
+    }
+
+    /// Moves the slots left in the caches of exited threads back onto
+    /// the free list. Returns `false` if there were none.
+    fn reclaim(&self, shard: usize) -> bool {
+        let indices = self.magazines.drain();
+        unsafe { self.push_batch(shard, &indices) };
+        !indices.is_empty()
+    }
+
+    /// The shard whose free list the calling thread uses.
+    fn shard(&self) -> usize {
+        // Spares allocators with a single list the thread local lookup.
+        if self.free.len() == 1 {
+            0
+        } else {
+            self.shards.current()
+        }
+    }
+
+    /// How many slots a thread cache moves from or to the free list at
+    /// once, few enough that a handful of caches cannot hold most of
+    /// them between them.
+    fn batch(&self) -> usize {
+        (self.storage.len() / 8).clamp(1, MAGAZINE_SIZE / 2)
+    }
+
+    fn pop(&self, shard: usize) -> Option<usize> {
+        let next =
+            |index| Some(&*self.storage.get(index as usize)?.next);
+        pop_slot::<B>(&self.free[shard], next)
+            .map(|index| index as usize)
+    }
+
+    /// Pops up to `max` slots off the free list of `shard` with a
+    /// single CAS and appends them to `out`. Returns `false` if the
+    /// list is empty.
+    fn pop_batch(
+        &self,
+        shard: usize,
+        max: usize,
+        out: &mut Vec<usize>,
+    ) -> bool {
+        let free = &self.free[shard];
+        let start = out.len();
+        let mut backoff = B::default();
+        let mut head = Head::unpack(free.load(Acquire));
+
+        loop {
+            let mut index = head.index;
+
+            // As in `pop`, the links may be stale, but then the tag has
+            // changed and the CAS fails.
+            while out.len() - start < max {
+                match self.storage.get(index as usize) {
+                    Some(slot) => {
+                        out.push(index as usize);
+                        index = slot.next.load(Relaxed);
+                    }
+                    None => break,
                 }
+            }
+
+            if out.len() == start {
+                return false;
+            }
+
+            match free.compare_exchange_weak(
+                head.pack(),
+                head.with_index(index).pack(),
+                Acquire,
+                Acquire,
+            ) {
+                Ok(_) => return true,
                 Err(new_head) => {
-                    head = new_head;
+                    backoff.backoff();
+                    out.truncate(start);
+                    head = Head::unpack(new_head);
                 }
             }
         }
     }
 
-    unsafe fn get_ref(&self, index: usize) -> &T {
-        (&*(self.storage.get_unchecked(index).data.get()
-            as *const MaybeUninit<T>))
-            .assume_init_ref()
+    /// Links `indices` in order and pushes them onto the free list of
+    /// `shard` with a single CAS.
+    unsafe fn push_batch(&self, shard: usize, indices: &[usize]) {
+        if let (Some(&first), Some(&last)) =
+            (indices.first(), indices.last())
+        {
+            for pair in indices.windows(2) {
+                self.storage
+                    .get_unchecked(pair[0])
+                    .next
+                    .store(pair[1] as u32, Relaxed);
+            }
+
+            self.push(shard, first, last);
+            self.waiters.notify_many(indices.len());
+        }
+    }
+
+    /// Pushes the chain of slots from `first` to `last`, which must
+    /// already be linked through their `next` fields, onto the free
+    /// list of `shard`.
+    unsafe fn push(&self, shard: usize, first: usize, last: usize) {
+        let last = &self.storage.get_unchecked(last).next;
+        push_chain::<B>(&self.free[shard], first as u32, last);
+    }
+
+    unsafe fn get_ref_unchecked(&self, index: usize) -> &T {
+        let slot = self.storage.get_unchecked(index);
+        slot.data.with(|data| (*data).assume_init_ref())
     }
 
     #[allow(clippy::mut_from_ref)]
-    unsafe fn get_mut(&self, index: usize) -> &mut T {
-        (&mut *self.storage.get_unchecked(index).data.get())
-            .assume_init_mut()
+    unsafe fn get_mut_unchecked(&self, index: usize) -> &mut T {
+        let slot = self.storage.get_unchecked(index);
+        slot.data.with_mut(|data| (*data).assume_init_mut())
     }
 
     unsafe fn deallocate(&self, index: usize) {
-        let mut head = self.free.load(SeqCst);
+        self.push(self.shard(), index, index);
+    }
 
-        loop {
-            self.storage.get_unchecked(index).next.store(head, SeqCst);
+    unsafe fn take(&self, index: usize) -> T {
+        let slot = self.storage.get_unchecked(index);
+        slot.data.with(|data| (*data).assume_init_read())
+    }
+}
 
-            match self.free.compare_exchange_weak(
-                head,
-                index as isize,
-                SeqCst,
-                SeqCst,
-            ) {
-                Ok(_) => break,
-                Err(new_head) => {
-                    head = new_head;
-                }
+#[cfg(feature = "std")]
+impl<T, B> Drop for Allocator<T, B> {
+    fn drop(&mut self) {
+        for index in 0..self.storage.len() {
+            let slot = unsafe { self.storage.get_unchecked(index) };
+
+            // Only values stored with `insert` can outlive their `Box`.
+            if slot.generation.load(Relaxed) % 2 == 1 {
+                slot.data.with_mut(|data| unsafe {
+                    (*data).assume_init_drop();
+                });
             }
         }
     }
+}
+
+#[cfg(feature = "std")]
+impl<T, B: Backoff> PoolAllocator<T> for Allocator<T, B> {
+    type Box<'a>
+        = Box<'a, T, B>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self {
+        Self::with_backoff(capacity)
+    }
 
-    unsafe fn drop_in_place(&self, index: usize) {
-        (&mut *self.storage.get_unchecked(index).data.get())
-            .assume_init_drop();
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T, B>, OutOfMemory<T>> {
+        self.try_box_it(value)
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.waiters
     }
 }
 
-pub struct Box<'a, T: Debug> {
-    allocator: &'a Allocator<T>,
+#[cfg(feature = "std")]
+pub type OwnedBox<T, B = SpinThenYield> =
+    crate::owned::OwnedBox<Allocator<T, B>, T>;
+
+#[cfg(feature = "std")]
+pub struct Box<'a, T, B: Backoff = SpinThenYield> {
+    allocator: &'a Allocator<T, B>,
     index: usize,
+    // Gives `Box` the auto traits of `&mut T` rather than those of the
+    // allocator, which is `Sync` for every `T: Send`.
+    _marker: PhantomData<&'a mut T>,
 }
 
-impl<T: Debug> Deref for Box<'_, T> {
+#[cfg(feature = "std")]
+impl<T, B: Backoff> Deref for Box<'_, T, B> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        unsafe { self.allocator.get_ref(self.index) }
+        unsafe { self.allocator.get_ref_unchecked(self.index) }
     }
 }
 
-impl<T: Debug> DerefMut for Box<'_, T> {
+#[cfg(feature = "std")]
+impl<T, B: Backoff> DerefMut for Box<'_, T, B> {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe { self.allocator.get_mut(self.index) }
+        unsafe { self.allocator.get_mut_unchecked(self.index) }
     }
 }
 
-impl<T: Debug> Drop for Box<'_, T> {
+#[cfg(feature = "std")]
+impl<T, B: Backoff> Drop for Box<'_, T, B> {
     fn drop(&mut self) {
-        unsafe { self.allocator.drop_in_place(self.index) };
+        // Released first, so that a panic in `T::drop` cannot leak it.
+        let value = unsafe { self.allocator.take(self.index) };
         unsafe { self.allocator.deallocate(self.index) };
+        self.allocator.waiters.notify();
+        std::mem::drop(value);
+    }
+}
+
+#[cfg(feature = "std")]
+pub struct ThreadCache<'allocator, T, B: Backoff = SpinThenYield> {
+    allocator: &'allocator Allocator<T, B>,
+    magazine: Magazine,
+}
+
+#[cfg(feature = "std")]
+impl<T, B: Backoff> ThreadCache<'_, T, B> {
+    #[track_caller]
+    pub fn box_it(&self, value: T) -> CachedBox<'_, T, B> {
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<CachedBox<'_, T, B>, OutOfMemory<T>> {
+        match self.acquire() {
+            Some(index) => {
+                let slot = unsafe {
+                    self.allocator.storage.get_unchecked(index)
+                };
+                slot.data
+                    .with_mut(|data| unsafe { (*data).write(value) });
+
+                Ok(CachedBox {
+                    cache: self,
+                    index,
+                    _marker: PhantomData,
+                })
+            }
+            None => Err(OutOfMemory(value)),
+        }
+    }
+
+    fn acquire(&self) -> Option<usize> {
+        let mut magazine = self.magazine.borrow_mut();
+
+        if let Some(index) = magazine.pop() {
+            return Some(index);
+        }
+
+        let (shard, batch) =
+            (self.allocator.shard(), self.allocator.batch());
+
+        if self.allocator.pop_batch(shard, batch, &mut magazine) {
+            return magazine.pop();
+        }
+
+        self.allocator.allocate()
+    }
+
+    fn release(&self, index: usize) {
+        let allocator = self.allocator;
+
+        // Nobody else can take slots from the magazine, so a waiter
+        // gets this one directly.
+        if !allocator.waiters.is_empty() {
+            unsafe { allocator.push(allocator.shard(), index, index) };
+            allocator.waiters.notify();
+            return;
+        }
+
+        let mut magazine = self.magazine.borrow_mut();
+        let batch = allocator.batch();
+
+        if magazine.len() >= 2 * batch {
+            let rest = magazine.split_off(batch);
+            unsafe { allocator.push_batch(allocator.shard(), &rest) };
+        }
+
+        magazine.push(index);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, B: Backoff> Drop for ThreadCache<'_, T, B> {
+    fn drop(&mut self) {
+        // Only when the magazine is not registered with the thread,
+        // which returns its slots on exit otherwise.
+        if Rc::strong_count(&self.magazine) == 1 {
+            let indices = self.magazine.take();
+            let shard = self.allocator.shard();
+            unsafe { self.allocator.push_batch(shard, &indices) };
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub struct CachedBox<'cache, T, B: Backoff = SpinThenYield> {
+    cache: &'cache ThreadCache<'cache, T, B>,
+    index: usize,
+    _marker: PhantomData<&'cache mut T>,
+}
+
+#[cfg(feature = "std")]
+impl<T, B: Backoff> Deref for CachedBox<'_, T, B> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.cache.allocator.get_ref_unchecked(self.index) }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, B: Backoff> DerefMut for CachedBox<'_, T, B> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.cache.allocator.get_mut_unchecked(self.index) }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, B: Backoff> Drop for CachedBox<'_, T, B> {
+    fn drop(&mut self) {
+        let value = unsafe { self.cache.allocator.take(self.index) };
+        self.cache.release(self.index);
+        std::mem::drop(value);
     }
 }
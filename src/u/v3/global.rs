@@ -0,0 +1,218 @@
+#[cfg(test)]
+mod tests;
+
+use crate::backoff::Spin;
+use crate::u::v3::pop_slot;
+use crate::u::v3::push_chain;
+use crate::u::v3::AtomicHead;
+use crate::u::v3::Head;
+use crate::u::v3::INVALID_INDEX;
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::ptr;
+use std::sync::atomic::AtomicPtr;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+const CLASSES: [usize; 4] = [16, 32, 64, 128];
+
+const UNINITIALIZED: u8 = 0;
+const INITIALIZING: u8 = 1;
+const READY: u8 = 2;
+const UNAVAILABLE: u8 = 3;
+
+/// A [`GlobalAlloc`] that serves layouts of up to 128 bytes from one
+/// pool per size class and everything else from [`System`].
+///
+/// Each pool reserves its blocks from [`System`] on first use, aligned
+/// to 128 bytes, so a block is aligned to its own size. Allocations
+/// that find their pool exhausted or still being set up by another
+/// thread fall back to [`System`] as well.
+#[derive(Debug)]
+pub struct SizeClasses {
+    pools: [RawPool; CLASSES.len()],
+}
+
+impl SizeClasses {
+    pub const fn new() -> Self {
+        Self::with_capacity(4096)
+    }
+
+    /// Reserves `capacity` blocks per size class.
+    pub const fn with_capacity(capacity: usize) -> Self {
+        assert!(1 <= capacity && capacity < (INVALID_INDEX as usize));
+
+        Self {
+            pools: [
+                RawPool::new(CLASSES[0], capacity),
+                RawPool::new(CLASSES[1], capacity),
+                RawPool::new(CLASSES[2], capacity),
+                RawPool::new(CLASSES[3], capacity),
+            ],
+        }
+    }
+
+    /// Whether `ptr` points into one of the size-class pools.
+    pub fn owns(&self, ptr: *const u8) -> bool {
+        self.pools.iter().any(|pool| pool.index_of(ptr).is_some())
+    }
+
+    fn pool(&self, layout: Layout) -> Option<&RawPool> {
+        let size = layout.size().max(layout.align());
+        self.pools.iter().find(|pool| size <= pool.block_size)
+    }
+}
+
+impl Default for SizeClasses {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for SizeClasses {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.pool(layout).and_then(RawPool::allocate) {
+            Some(block) => block,
+            None => System.alloc(layout),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(pool) = self.pool(layout) {
+            if let Some(index) = pool.index_of(ptr) {
+                pool.push(index);
+                return;
+            }
+        }
+
+        System.dealloc(ptr, layout);
+    }
+}
+
+/// The free list of `u::v3` over untyped blocks. The `next` links live
+/// in a separate array behind the blocks, so a block handed out to a
+/// caller is never read by a concurrent `pop`.
+#[derive(Debug)]
+struct RawPool {
+    block_size: usize,
+    capacity: usize,
+    state: AtomicU8,
+    blocks: AtomicPtr<u8>,
+    next: AtomicPtr<AtomicU32>,
+    free: AtomicHead,
+}
+
+impl RawPool {
+    const fn new(block_size: usize, capacity: usize) -> Self {
+        Self {
+            block_size,
+            capacity,
+            state: AtomicU8::new(UNINITIALIZED),
+            blocks: AtomicPtr::new(ptr::null_mut()),
+            next: AtomicPtr::new(ptr::null_mut()),
+            free: AtomicHead::new(0),
+        }
+    }
+
+    fn layout(&self) -> (Layout, usize) {
+        let blocks = Layout::from_size_align(
+            self.block_size * self.capacity,
+            128,
+        )
+        .unwrap();
+        let next = Layout::array::<AtomicU32>(self.capacity).unwrap();
+        blocks.extend(next).unwrap()
+    }
+
+    fn initialize(&self) -> bool {
+        if let Err(state) = self.state.compare_exchange(
+            UNINITIALIZED,
+            INITIALIZING,
+            Acquire,
+            Acquire,
+        ) {
+            return state == READY;
+        }
+
+        let (layout, offset) = self.layout();
+        let blocks = unsafe { System.alloc(layout) };
+
+        if blocks.is_null() {
+            self.state.store(UNAVAILABLE, Release);
+            return false;
+        }
+
+        let next = unsafe { blocks.add(offset) }.cast::<AtomicU32>();
+
+        for index in 0..self.capacity {
+            let link = if index + 1 < self.capacity {
+                index as u32 + 1
+            } else {
+                INVALID_INDEX
+            };
+
+            unsafe { next.add(index).write(AtomicU32::new(link)) };
+        }
+
+        self.blocks.store(blocks, Relaxed);
+        self.next.store(next, Relaxed);
+        self.free.store(Head { tag: 0, index: 0 }.pack(), Relaxed);
+        self.state.store(READY, Release);
+        true
+    }
+
+    fn allocate(&self) -> Option<*mut u8> {
+        if self.state.load(Acquire) != READY && !self.initialize() {
+            return None;
+        }
+
+        let index = self.pop()?;
+        let blocks = self.blocks.load(Relaxed);
+        Some(unsafe { blocks.add(index * self.block_size) })
+    }
+
+    fn index_of(&self, ptr: *const u8) -> Option<usize> {
+        if self.state.load(Acquire) != READY {
+            return None;
+        }
+
+        let blocks = self.blocks.load(Relaxed) as usize;
+        let offset = (ptr as usize).checked_sub(blocks)?;
+
+        if offset < self.block_size * self.capacity {
+            Some(offset / self.block_size)
+        } else {
+            None
+        }
+    }
+
+    /// Must only be called once the pool is ready.
+    unsafe fn next(&self, index: usize) -> &AtomicU32 {
+        &*self.next.load(Relaxed).add(index)
+    }
+
+    fn pop(&self) -> Option<usize> {
+        let next = |index| {
+            let valid = (index as usize) < self.capacity;
+            valid.then(|| unsafe { self.next(index as usize) })
+        };
+
+        pop_slot::<Spin>(&self.free, next).map(|index| index as usize)
+    }
+
+    fn push(&self, index: usize) {
+        let next = unsafe { self.next(index) };
+        push_chain::<Spin>(&self.free, index as u32, next);
+    }
+}
+
+impl Drop for RawPool {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            let (layout, _) = self.layout();
+            unsafe { System.dealloc(*self.blocks.get_mut(), layout) };
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+
+#[test]
+fn small_layouts_come_from_the_pools() {
+    let a = super::SizeClasses::with_capacity(2);
+
+    for &(size, align) in &[(1, 1), (8, 8), (24, 8), (64, 64), (128, 1)]
+    {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        let ptr = unsafe { a.alloc(layout) };
+        assert!(a.owns(ptr));
+        assert_eq!(0, ptr as usize % align);
+        unsafe { a.dealloc(ptr, layout) };
+    }
+}
+
+#[test]
+fn large_and_over_aligned_layouts_fall_back() {
+    let a = super::SizeClasses::with_capacity(2);
+
+    for &(size, align) in &[(129, 8), (4096, 8), (8, 256)] {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        let ptr = unsafe { a.alloc(layout) };
+        assert!(!ptr.is_null());
+        assert!(!a.owns(ptr));
+        assert_eq!(0, ptr as usize % align);
+        unsafe { a.dealloc(ptr, layout) };
+    }
+}
+
+#[test]
+fn exhausted_pool_falls_back() {
+    let a = super::SizeClasses::with_capacity(2);
+    let layout = Layout::new::<u64>();
+    let ptrs: Vec<_> =
+        (0..3).map(|_| unsafe { a.alloc(layout) }).collect();
+    assert!(a.owns(ptrs[0]));
+    assert!(a.owns(ptrs[1]));
+    assert!(!a.owns(ptrs[2]));
+
+    for &ptr in &ptrs {
+        unsafe { a.dealloc(ptr, layout) };
+    }
+
+    let ptr = unsafe { a.alloc(layout) };
+    assert!(a.owns(ptr));
+    unsafe { a.dealloc(ptr, layout) };
+}
+
+#[test]
+fn realloc_moves_between_classes() {
+    let a = super::SizeClasses::with_capacity(2);
+    let layout = Layout::from_size_align(16, 8).unwrap();
+    let ptr = unsafe { a.alloc(layout) };
+    unsafe { ptr.write_bytes(7, 16) };
+    let ptr = unsafe { a.realloc(ptr, layout, 512) };
+    assert!(!a.owns(ptr));
+    assert_eq!(&[7; 16], unsafe {
+        std::slice::from_raw_parts(ptr, 16)
+    });
+    let layout = Layout::from_size_align(512, 8).unwrap();
+    unsafe { a.dealloc(ptr, layout) };
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests;
+
+use crate::backoff::Backoff;
+use crate::backoff::ExponentialSpin;
+use crate::error::OutOfMemory;
+use crate::u::v3::slice;
+use crate::u::v3::AtomicHead;
+use crate::u::v3::Head;
+use crate::u::v3::Slot;
+use crate::u::v3::INVALID_INDEX;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+pub use crate::u::v3::slice::Box;
+
+/// The free list of [`u::v3`](super) over `N` slots stored inline,
+/// which can be built at compile time:
+///
+/// ```
+/// use allocator::u::v3::inline::Allocator;
+///
+/// static POOL: Allocator<u64, 256> = Allocator::new();
+///
+/// assert_eq!(123, *POOL.box_it(123));
+/// ```
+pub struct Allocator<T, const N: usize, B = ExponentialSpin> {
+    slots: [Slot<T>; N],
+    free: AtomicHead,
+    _backoff: PhantomData<fn() -> B>,
+}
+
+unsafe impl<T: Send, const N: usize, B> Sync for Allocator<T, N, B> {}
+
+impl<T, const N: usize, B: Backoff> Allocator<T, N, B> {
+    pub const fn new() -> Self {
+        assert!(1 <= N && N < (INVALID_INDEX as usize));
+        let mut slots = [const { MaybeUninit::<Slot<T>>::uninit() }; N];
+        let mut index = 0;
+
+        while index < N {
+            let next = if index + 1 < N {
+                index as u32 + 1
+            } else {
+                INVALID_INDEX
+            };
+
+            slots[index] = MaybeUninit::new(Slot::empty(next));
+            index += 1;
+        }
+
+        // Every element has been initialised above.
+        let slots =
+            unsafe { (&raw const slots).cast::<[Slot<T>; N]>().read() };
+
+        Self {
+            slots,
+            free: AtomicHead::new(Head { tag: 0, index: 0 }.pack()),
+            _backoff: PhantomData,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[track_caller]
+    pub fn box_it(&self, value: T) -> Box<'_, T, B> {
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T, B>, OutOfMemory<T>> {
+        slice::try_box_it(&self.slots, &self.free, value)
+    }
+}
+
+impl<T, const N: usize, B: Backoff> Default for Allocator<T, N, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
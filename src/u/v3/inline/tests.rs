@@ -0,0 +1,48 @@
+use std::mem::drop;
+
+static POOL: super::Allocator<i64, 4> = super::Allocator::new();
+
+#[test]
+fn equality() {
+    let a = super::Allocator::<i64, 1>::new();
+    let b = a.box_it(123);
+    assert_eq!(123, *b);
+}
+
+#[test]
+fn error_when_out_of_memory() {
+    let a = super::Allocator::<i64, 1>::new();
+    let b = a.try_box_it(123).unwrap();
+    let c = a.try_box_it(234);
+    assert_eq!(234, c.err().unwrap().into_inner());
+    drop(b);
+    assert_eq!(345, *a.box_it(345));
+}
+
+#[test]
+fn static_pool_across_threads() {
+    assert_eq!(4, POOL.capacity());
+
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            s.spawn(move || {
+                for i in 0..10_000 {
+                    let mut b = POOL.box_it(t);
+                    *b += i;
+                    assert_eq!(t + i, *b);
+                }
+            });
+        }
+    });
+
+    let boxes: Vec<_> = (0..4).map(|i| POOL.box_it(i)).collect();
+    assert!(POOL.try_box_it(4).is_err());
+    drop(boxes);
+}
+
+#[test]
+fn boxes_move_between_threads() {
+    let b = POOL.box_it(123);
+    let b = std::thread::spawn(move || *b).join().unwrap();
+    assert_eq!(123, b);
+}
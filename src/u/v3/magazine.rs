@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests;
+
+use crate::sync::Mutex;
+use crate::sync::MutexGuard;
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::PoisonError;
+use std::sync::Weak;
+
+/// Free slots that one thread keeps for itself. Nothing but that
+/// thread ever touches them, so they need no lock.
+pub(super) type Magazine = Rc<RefCell<Vec<usize>>>;
+
+/// The slots that threads have left in their magazines when they
+/// exited, for the allocator to take back when it runs out.
+///
+/// Each thread gets its magazine on its first call to
+/// [`Magazines::current`]. The magazines of live threads are never
+/// taken from.
+#[derive(Debug)]
+pub(super) struct Magazines {
+    returned: Arc<Mutex<Vec<usize>>>,
+}
+
+struct Entry {
+    returned: Weak<Mutex<Vec<usize>>>,
+    magazine: Magazine,
+}
+
+thread_local! {
+    static ENTRIES: RefCell<Vec<Entry>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+impl Magazines {
+    pub(super) fn new() -> Self {
+        Self {
+            returned: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// The magazine of the calling thread, which is registered on first
+    /// use.
+    pub(super) fn current(&self) -> Magazine {
+        ENTRIES
+            .try_with(|entries| {
+                let mut entries = entries.borrow_mut();
+                let this = Arc::as_ptr(&self.returned);
+
+                match entries
+                    .iter()
+                    .find(|e| e.returned.as_ptr() == this)
+                {
+                    Some(entry) => entry.magazine.clone(),
+                    None => {
+                        entries
+                            .retain(|e| e.returned.strong_count() != 0);
+                        let magazine = Magazine::default();
+
+                        entries.push(Entry {
+                            returned: Arc::downgrade(&self.returned),
+                            magazine: magazine.clone(),
+                        });
+
+                        magazine
+                    }
+                }
+            })
+            // The thread is exiting and its entries are gone, so the
+            // magazine is not registered, and whoever holds it last has
+            // to return its slots.
+            .unwrap_or_default()
+    }
+
+    /// Takes the slots that exited threads have returned.
+    pub(super) fn drain(&self) -> Vec<usize> {
+        mem::take(&mut *lock(&self.returned))
+    }
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        if let Some(returned) = self.returned.upgrade() {
+            lock(&returned).append(&mut self.magazine.borrow_mut());
+        }
+    }
+}
+
+/// Locks `mutex`, whose data is consistent even after a panic, because
+/// nothing that panics runs while it is held.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,42 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[test]
+fn current_is_per_thread() {
+    let m = super::Magazines::new();
+    assert!(Rc::ptr_eq(&m.current(), &m.current()));
+    m.current().borrow_mut().push(1);
+
+    std::thread::scope(|s| {
+        s.spawn(|| assert!(m.current().borrow().is_empty()));
+    });
+
+    assert_eq!(vec![1], *m.current().borrow());
+}
+
+#[test]
+fn drain_leaves_live_magazines_alone() {
+    let m = Arc::new(super::Magazines::new());
+    m.current().borrow_mut().extend([1, 2]);
+    let n = m.clone();
+    // Unlike a scoped thread, joined only after its thread locals are
+    // gone.
+    std::thread::spawn(move || n.current().borrow_mut().push(3))
+        .join()
+        .unwrap();
+
+    assert_eq!(vec![3], m.drain());
+    assert!(m.drain().is_empty());
+    assert_eq!(vec![1, 2], *m.current().borrow());
+}
+
+#[test]
+fn exiting_threads_return_their_slots() {
+    let m = Arc::new(super::Magazines::new());
+    let n = m.clone();
+    std::thread::spawn(move || n.current().borrow_mut().push(1))
+        .join()
+        .unwrap();
+
+    assert_eq!(vec![1], *super::lock(&m.returned));
+}
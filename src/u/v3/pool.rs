@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests;
+
+use crate::u::v3::Allocator;
+use crate::u::v3::KeyedSlot;
+use std::alloc::AllocError;
+use std::alloc::Layout;
+use std::mem::offset_of;
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+
+/// Hands out the slots of a [`u::v3::Allocator`](Allocator) as raw
+/// memory, so they can back standard collections, e.g. through
+/// `std::boxed::Box::new_in(value, &pool)`. Requests whose layout does
+/// not fit into a `T` fail with [`AllocError`].
+#[derive(Debug)]
+pub struct Pool<T> {
+    slots: Allocator<MaybeUninit<T>>,
+}
+
+impl<T> Pool<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: Allocator::new(capacity),
+        }
+    }
+
+    pub fn growable(capacity: usize, max_capacity: usize) -> Self {
+        Self {
+            slots: Allocator::growable(capacity, max_capacity),
+        }
+    }
+}
+
+unsafe impl<T> std::alloc::Allocator for Pool<T> {
+    fn allocate(
+        &self,
+        layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let slot_layout = Layout::new::<T>();
+
+        if layout.size() > slot_layout.size()
+            || layout.align() > slot_layout.align()
+        {
+            return Err(AllocError);
+        }
+
+        let index = self.slots.allocate().ok_or(AllocError)?;
+        let slot = unsafe { self.slots.storage.get_unchecked(index) };
+        let data = slot
+            .data
+            .with_mut(|data| unsafe { NonNull::new_unchecked(data) });
+        Ok(NonNull::slice_from_raw_parts(
+            data.cast(),
+            slot_layout.size(),
+        ))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, _layout: Layout) {
+        let offset = offset_of!(KeyedSlot<MaybeUninit<T>>, data);
+        let slot = ptr.as_ptr().sub(offset).cast();
+        let index = self.slots.storage.index_of(slot);
+        debug_assert!(index.is_some(), "pointer not from this pool");
+        self.slots.deallocate(index.unwrap_unchecked());
+    }
+}
@@ -0,0 +1,44 @@
+use std::mem::drop;
+
+#[test]
+fn box_new_in() {
+    let pool = super::Pool::<i64>::new(1);
+    let b = Box::new_in(123, &pool);
+    assert_eq!(123, *b);
+    assert!(Box::try_new_in(234, &pool).is_err());
+    drop(b);
+    let c = Box::new_in(345, &pool);
+    assert_eq!(345, *c);
+}
+
+#[test]
+fn vec_with_capacity_in() {
+    let pool = super::Pool::<[u16; 4]>::new(2);
+    let mut v = Vec::<u16, _>::with_capacity_in(4, &pool);
+    v.extend([1, 2, 3, 4]);
+    let w = Vec::<u8, _>::with_capacity_in(8, &pool);
+    assert_eq!(&[1, 2, 3, 4], &v[..]);
+    assert!(Vec::<u8, _>::try_with_capacity_in(1, &pool).is_err());
+    drop((v, w));
+}
+
+#[test]
+fn rejects_layouts_that_do_not_fit() {
+    let pool = super::Pool::<u32>::new(1);
+    assert!(Box::try_new_in(0u64, &pool).is_err());
+    assert!(Box::try_new_in([0u8; 5], &pool).is_err());
+    assert!(Box::try_new_in(0u16, &pool).is_ok());
+}
+
+#[test]
+fn deallocates_into_grown_segments() {
+    let pool = super::Pool::<i64>::growable(1, 3);
+    let boxes: Vec<_> = (0..3).map(|i| Box::new_in(i, &pool)).collect();
+    assert!(Box::try_new_in(3, &pool).is_err());
+    drop(boxes);
+    let boxes: Vec<_> = (0..3).map(|i| Box::new_in(i, &pool)).collect();
+    assert_eq!(
+        vec![0, 1, 2],
+        boxes.iter().map(|b| **b).collect::<Vec<_>>()
+    );
+}
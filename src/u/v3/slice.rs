@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests;
+
+use crate::backoff::Backoff;
+use crate::backoff::ExponentialSpin;
+use crate::error::OutOfMemory;
+use crate::u::v3::pop_slot;
+use crate::u::v3::push_chain;
+use crate::u::v3::AtomicHead;
+use crate::u::v3::Head;
+use crate::u::v3::Slot;
+use crate::u::v3::INVALID_INDEX;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::ops::DerefMut;
+
+/// The free list of [`u::v3`](super) over storage provided by the
+/// caller, which needs neither `std` nor a heap:
+///
+/// ```
+/// use allocator::u::v3::slice::Allocator;
+/// use allocator::u::v3::Slot;
+/// use std::mem::MaybeUninit;
+///
+/// let mut storage = [const { MaybeUninit::<Slot<u32>>::uninit() }; 8];
+/// let pool = Allocator::<u32>::new(&mut storage);
+/// assert_eq!(123, *pool.box_it(123));
+/// ```
+pub struct Allocator<'s, T, B = ExponentialSpin> {
+    slots: &'s [Slot<T>],
+    free: AtomicHead,
+    _backoff: PhantomData<fn() -> B>,
+}
+
+unsafe impl<T: Send, B> Sync for Allocator<'_, T, B> {}
+
+impl<'s, T, B: Backoff> Allocator<'s, T, B> {
+    pub fn new(storage: &'s mut [MaybeUninit<Slot<T>>]) -> Self {
+        let capacity = storage.len();
+        assert!(1 <= capacity && capacity < (INVALID_INDEX as usize));
+
+        for (index, slot) in storage.iter_mut().enumerate() {
+            let next = if index + 1 < capacity {
+                index as u32 + 1
+            } else {
+                INVALID_INDEX
+            };
+
+            slot.write(Slot::empty(next));
+        }
+
+        let slots = storage as *mut [MaybeUninit<Slot<T>>] as *const _;
+
+        Self {
+            slots: unsafe { &*slots },
+            free: AtomicHead::new(Head { tag: 0, index: 0 }.pack()),
+            _backoff: PhantomData,
+        }
+    }
+
+    /// Leaks a heap allocation for `capacity` slots, which is an easy
+    /// way to get storage for an allocator that lives forever.
+    #[cfg(feature = "alloc")]
+    pub fn leak_storage(
+        capacity: usize,
+    ) -> &'static mut [MaybeUninit<Slot<T>>] {
+        alloc::boxed::Box::leak(alloc::boxed::Box::new_uninit_slice(
+            capacity,
+        ))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[track_caller]
+    pub fn box_it(&self, value: T) -> Box<'_, T, B> {
+        self.try_box_it(value).expect("out of reserved memory")
+    }
+
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T, B>, OutOfMemory<T>> {
+        try_box_it(self.slots, &self.free, value)
+    }
+}
+
+/// Also used by [`inline::Allocator`](super::inline::Allocator),
+/// which only differs in where the slots are stored.
+pub(super) fn try_box_it<'a, T, B: Backoff>(
+    slots: &'a [Slot<T>],
+    free: &'a AtomicHead,
+    value: T,
+) -> Result<Box<'a, T, B>, OutOfMemory<T>> {
+    let next = |index| Some(&*slots.get(index as usize)?.next);
+
+    match pop_slot::<B>(free, next) {
+        Some(index) => {
+            let slot = &slots[index as usize];
+            slot.data.with_mut(|data| unsafe { (*data).write(value) });
+
+            Ok(Box {
+                free,
+                slot,
+                index,
+                _marker: PhantomData,
+                _backoff: PhantomData,
+            })
+        }
+        None => Err(OutOfMemory(value)),
+    }
+}
+
+pub struct Box<'a, T, B: Backoff = ExponentialSpin> {
+    free: &'a AtomicHead,
+    slot: &'a Slot<T>,
+    index: u32,
+    // Gives `Box` the auto traits of `&mut T` rather than those of the
+    // allocator, which is `Sync` for every `T: Send`.
+    _marker: PhantomData<&'a mut T>,
+    _backoff: PhantomData<fn() -> B>,
+}
+
+impl<T, B: Backoff> Deref for Box<'_, T, B> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.slot
+            .data
+            .with(|data| unsafe { (*data).assume_init_ref() })
+    }
+}
+
+impl<T, B: Backoff> DerefMut for Box<'_, T, B> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.slot
+            .data
+            .with_mut(|data| unsafe { (*data).assume_init_mut() })
+    }
+}
+
+impl<T, B: Backoff> Drop for Box<'_, T, B> {
+    fn drop(&mut self) {
+        let value = self
+            .slot
+            .data
+            .with(|data| unsafe { (*data).assume_init_read() });
+        push_chain::<B>(self.free, self.index, &self.slot.next);
+        core::mem::drop(value);
+    }
+}
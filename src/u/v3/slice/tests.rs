@@ -0,0 +1,59 @@
+use std::mem::drop;
+use std::mem::MaybeUninit;
+use std::ptr::addr_of_mut;
+
+type Storage<const N: usize> = [MaybeUninit<super::Slot<i64>>; N];
+
+fn storage<const N: usize>() -> Storage<N> {
+    [const { MaybeUninit::uninit() }; N]
+}
+
+#[cfg(feature = "std")]
+crate::conformance::suite!(
+    |capacity| {
+        let storage = super::Allocator::<_>::leak_storage(capacity);
+        super::Allocator::<_>::new(storage)
+    },
+    Lifo
+);
+
+#[test]
+fn static_storage() {
+    static mut STORAGE: Storage<4> =
+        [const { MaybeUninit::uninit() }; 4];
+    let storage = unsafe { &mut *addr_of_mut!(STORAGE) };
+    let a: super::Allocator<'static, i64> =
+        super::Allocator::new(storage);
+    assert_eq!(4, a.capacity());
+    let boxes: Vec<_> = (0..4).map(|i| a.box_it(i)).collect();
+    assert!(a.try_box_it(4).is_err());
+    drop(boxes);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn leaked_storage() {
+    let storage = super::Allocator::<i64>::leak_storage(2);
+    let a = super::Allocator::<i64>::new(storage);
+    assert_eq!(2, a.capacity());
+}
+
+#[test]
+fn churn_at_capacity() {
+    let mut storage = storage::<4>();
+    let a = super::Allocator::<i64>::new(&mut storage);
+
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let a = &a;
+
+            s.spawn(move || {
+                for i in 0..10_000 {
+                    let mut b = a.box_it(t);
+                    *b += i;
+                    assert_eq!(t + i, *b);
+                }
+            });
+        }
+    });
+}
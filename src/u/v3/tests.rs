@@ -1,26 +1,333 @@
+use crate::executor::block_on;
+use crate::pool_allocator::PoolAllocator;
+use std::cell::Cell;
 use std::mem::drop;
 
+crate::conformance::suite!(
+    super::Allocator::new,
+    Lifo,
+    Growable(super::Allocator::growable)
+);
+
 #[test]
-fn equality() {
-    let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    assert_eq!(123, *b);
+fn no_double_handout_under_contention() {
+    let a = super::Allocator::<usize>::new(4);
+
+    std::thread::scope(|s| {
+        for id in 0..8 {
+            let a = &a;
+
+            s.spawn(move || {
+                for _ in 0..20_000 {
+                    if let Ok(mut b) = a.try_box_it(id) {
+                        for _ in 0..8 {
+                            assert_eq!(id, *b);
+                            *b = id;
+                            std::hint::spin_loop();
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let boxes: Vec<_> = (0..4).map(|i| a.box_it(i)).collect();
+    assert!(a.try_box_it(4).is_err());
+    drop(boxes);
+}
+
+#[test]
+fn head_round_trips() {
+    let head = super::Head {
+        tag: 123,
+        index: super::INVALID_INDEX,
+    };
+    assert_eq!(head, super::Head::unpack(head.pack()));
+    let next = head.with_index(0);
+    assert_eq!(next, super::Head::unpack(next.pack()));
+}
+
+#[test]
+fn head_tag_changes_on_reuse() {
+    let a = super::Allocator::<i64>::new(2);
+    let before = super::Head::unpack(a.free[0].load(super::Relaxed));
+    drop(a.box_it(123));
+    let after = super::Head::unpack(a.free[0].load(super::Relaxed));
+    assert_eq!(before.index, after.index);
+    assert_ne!(before.tag, after.tag);
+}
+
+#[test]
+fn grows_under_contention() {
+    let a = super::Allocator::<usize>::growable(1, 64);
+    let barrier = std::sync::Barrier::new(8);
+
+    std::thread::scope(|s| {
+        for id in 0..8 {
+            let (a, barrier) = (&a, &barrier);
+
+            s.spawn(move || {
+                let boxes: Vec<_> =
+                    (0..8).map(|i| a.box_it(8 * id + i)).collect();
+
+                barrier.wait();
+                assert!(a.try_box_it(64).is_err());
+
+                for (i, b) in boxes.iter().enumerate() {
+                    assert_eq!(8 * id + i, **b);
+                }
+
+                barrier.wait();
+            });
+        }
+    });
+
+    assert_eq!(64, a.storage.len());
 }
 
 #[test]
-#[should_panic]
-fn panic_when_out_of_memory() {
+fn sharded_takes_from_other_shards_last() {
+    let a = super::Allocator::<i64>::sharded(4, 4, 2);
+    let (own, other) = (a.shard(), 1 - a.shard());
+    let head = |shard: usize| {
+        super::Head::unpack(a.free[shard].load(super::Relaxed)).index
+    };
+    let before = head(other);
+    let mut boxes: Vec<_> = (0..2).map(|i| a.box_it(i)).collect();
+    assert_eq!(super::INVALID_INDEX, head(own));
+    assert_eq!(before, head(other));
+    boxes.extend((2..4).map(|i| a.box_it(i)));
+    assert!(a.try_box_it(4).is_err());
+    drop(boxes);
+    assert_eq!(super::INVALID_INDEX, head(other));
+}
+
+#[test]
+fn sharded_under_contention() {
+    let a = super::Allocator::<usize>::sharded(8, 8, 4);
+
+    std::thread::scope(|s| {
+        for id in 0..8 {
+            let a = &a;
+
+            s.spawn(move || {
+                for _ in 0..10_000 {
+                    if let Ok(mut b) = a.try_box_it(id) {
+                        assert_eq!(id, *b);
+                        *b = id;
+                    }
+                }
+            });
+        }
+    });
+
+    let boxes: Vec<_> = (0..8).map(|i| a.box_it(i)).collect();
+    assert!(a.try_box_it(8).is_err());
+    drop(boxes);
+}
+
+#[test]
+fn insert_get_remove() {
+    let mut a = super::Allocator::<i64>::new(2);
+    let k = a.insert(123);
+    assert_eq!(Some(&123), a.get(k));
+    *a.get_mut(k).unwrap() = 234;
+    assert_eq!(Some(234), a.remove(k));
+    assert_eq!(None, a.get(k));
+    assert_eq!(None, a.remove(k));
+}
+
+#[test]
+fn stale_key_after_reuse() {
+    let mut a = super::Allocator::<i64>::new(1);
+    let k = a.insert(123);
+    a.remove(k);
+    let l = a.insert(234);
+    assert_ne!(k, l);
+    assert_eq!(None, a.get(k));
+    assert_eq!(Some(&234), a.get(l));
+    let b = {
+        a.remove(l);
+        a.try_insert(345).unwrap()
+    };
+    assert!(a.try_insert(456).is_err());
+    assert_eq!(None, a.get(l));
+    assert_eq!(Some(&345), a.get(b));
+}
+
+#[test]
+fn drop_releases_inserted_values() {
+    let value = std::sync::Arc::new(());
+    let a = super::Allocator::new(2);
+    a.insert(value.clone());
+    drop(a.box_it(value.clone()));
+    assert_eq!(2, std::sync::Arc::strong_count(&value));
+    drop(a);
+    assert_eq!(1, std::sync::Arc::strong_count(&value));
+}
+
+#[test]
+fn thread_cache_reuses_local_slots() {
+    let a = super::Allocator::<i64>::new(64);
+    let cache = a.thread_cache();
+    drop(cache.box_it(123));
+    let head = a.free[0].load(super::Relaxed);
+
+    for i in 0..100 {
+        let b = cache.box_it(i);
+        assert_eq!(i, *b);
+    }
+
+    assert_eq!(head, a.free[0].load(super::Relaxed));
+}
+
+#[test]
+fn thread_cache_slots_stay_with_their_thread() {
+    let a = super::Allocator::<i64>::new(16);
+    let cache = a.thread_cache();
+    drop(cache.box_it(123));
+    let cached = cache.magazine.borrow().len() as i64;
+    assert_eq!(a.batch() as i64, cached);
+
+    let boxes: Vec<_> = (cached..16).map(|i| a.box_it(i)).collect();
+    assert!(a.try_box_it(16).is_err());
+    let mine: Vec<_> = (0..cached).map(|i| cache.box_it(i)).collect();
+    assert!(cache.try_box_it(16).is_err());
+    drop(mine);
+    drop(boxes);
+}
+
+#[test]
+fn thread_cache_takes_a_bounded_batch() {
+    let a = super::Allocator::<i64>::new(16);
+    let cache = a.thread_cache();
+    let b = cache.box_it(123);
+    assert_eq!(1, cache.magazine.borrow().len());
+    drop(b);
+
+    for i in 0..16 {
+        let b = a.box_it(i);
+        drop(cache.box_it(123));
+        drop(b);
+    }
+
+    assert!(cache.magazine.borrow().len() <= 4);
+}
+
+#[test]
+fn thread_cache_returns_slots_on_exit() {
+    let a = std::sync::Arc::new(super::Allocator::<i64>::new(16));
+    let b = a.clone();
+    // Unlike a scoped thread, joined only after its thread locals are
+    // gone.
+    std::thread::spawn(move || drop(b.thread_cache().box_it(123)))
+        .join()
+        .unwrap();
+    assert_eq!(2, a.magazines.drain().len());
+}
+
+#[test]
+fn thread_cache_wakes_waiters() {
     let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    let c = a.box_it(234);
-    drop((b, c));
+    let cache = a.thread_cache();
+    // Released into the cache, the slot would be out of their reach.
+    let waiting = || {
+        while a.waiters.is_empty() {
+            std::thread::yield_now();
+        }
+    };
+
+    std::thread::scope(|s| {
+        let b = cache.box_it(123);
+        let waiter = s.spawn(|| *a.box_it_wait(234));
+        waiting();
+        drop(b);
+        assert_eq!(234, waiter.join().unwrap());
+
+        let b = cache.box_it(123);
+        let waiter = s.spawn(|| *block_on(a.box_it_async(345)));
+        waiting();
+        drop(b);
+        assert_eq!(345, waiter.join().unwrap());
+    });
+}
+
+#[test]
+fn thread_cache_churn() {
+    let a = std::sync::Arc::new(super::Allocator::<usize>::new(64));
+
+    // Unlike scoped threads, joined only after their thread locals,
+    // and with them their caches, are gone.
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let a = a.clone();
+
+            std::thread::spawn(move || {
+                let cache = a.thread_cache();
+                let mut boxes = Vec::new();
+
+                for i in 0..10_000 {
+                    match cache.try_box_it(t * 10_000 + i) {
+                        Ok(b) => boxes.push(b),
+                        Err(_) => boxes.clear(),
+                    }
+
+                    if i % 7 == 0 {
+                        boxes.truncate(boxes.len() / 2);
+                    }
+                }
+
+                for (i, b) in boxes.iter().enumerate() {
+                    assert!(**b / 10_000 == t, "{}: {}", i, **b);
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let boxes: Vec<_> = (0..64).map(|i| a.box_it(i)).collect();
+    assert!(a.try_box_it(64).is_err());
+    drop(boxes);
+}
+
+#[test]
+fn no_debug_bound() {
+    struct NoDebug(i64);
+
+    let a = super::Allocator::new(1);
+    let b = a.box_it(NoDebug(123));
+    assert_eq!(123, b.0);
 }
 
 #[test]
-fn memory_reclamation() {
+fn sync_and_send() {
+    fn sync<T: Sync>(_: &T) {}
+    fn send<T: Send>(_: T) {}
+
     let a = super::Allocator::<i64>::new(1);
+    sync(&a);
     let b = a.box_it(123);
-    drop(b);
-    let c = a.box_it(234);
-    assert_eq!(234, *c);
+    sync(&b);
+    send(b);
+    send(a);
+}
+
+#[test]
+fn shared_without_sync_values() {
+    let mut a = super::Allocator::<Cell<i64>>::new(3);
+    let key = a.insert(Cell::new(123));
+
+    std::thread::scope(|s| {
+        let b = a.box_it(Cell::new(234));
+        s.spawn(move || {
+            b.set(b.get() + 1);
+            assert_eq!(235, b.get());
+        });
+        s.spawn(|| assert_eq!(345, a.box_it(Cell::new(345)).get()));
+    });
+
+    assert_eq!(Some(123), a.remove(key).map(Cell::into_inner));
 }
@@ -2,15 +2,26 @@
 mod tests;
 
 use crate::align128::Align128;
-use std::cell::UnsafeCell;
+use crate::backoff::Backoff;
+use crate::backoff::SpinThenYield;
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use crate::shard::Registration;
+use crate::shard::Shards;
+use crate::sync::AtomicBool;
+use crate::sync::AtomicUsize;
+use crate::sync::UnsafeCell;
+use crate::u::segments::Growth;
+use crate::u::segments::Segments;
+use crate::wait::NotifyOnDrop;
+use crate::wait::Waiters;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
 use std::mem::MaybeUninit;
 use std::ops::Deref;
 use std::ops::DerefMut;
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering::{
-    AcqRel, Acquire, Relaxed, Release, SeqCst,
-};
+use std::ops::Range;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
 
 #[derive(Default)]
 struct Mutex<T: ?Sized> {
@@ -38,7 +49,7 @@ impl<T> Mutex<T> {
         {
             Some(MutexGuard { mutex: self })
         } else {
-            std::process::abort()
+            None
         }
     }
 }
@@ -58,98 +69,226 @@ impl<T> Deref for MutexGuard<'_, T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        unsafe { &*(self.mutex.value.get() as *const T) }
+        self.mutex.value.with(|value| unsafe { &*value })
     }
 }
 
 impl<T> DerefMut for MutexGuard<'_, T> {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *self.mutex.value.get() }
+        self.mutex.value.with_mut(|value| unsafe { &mut *value })
     }
 }
 
-pub struct Allocator<T> {
-    storage: std::boxed::Box<[Align128<Mutex<MaybeUninit<T>>>]>,
-    indices: parking_lot::Mutex<std::boxed::Box<[u16]>>,
+type Slot<T> = Align128<Mutex<MaybeUninit<T>>>;
+
+fn segment<T>(range: Range<usize>) -> std::boxed::Box<[Slot<T>]> {
+    let mut segment = Vec::with_capacity(range.len());
+
+    segment.resize_with(range.len(), || {
+        Align128(Mutex::new(MaybeUninit::uninit()))
+    });
+
+    segment.into_boxed_slice()
+}
+
+pub struct Allocator<T, B = SpinThenYield> {
+    storage: Segments<Slot<T>>,
+    // The number of slots that are in use or reserved by a thread that
+    // is about to lock one.
+    occupied: AtomicUsize,
+    shards: Shards,
+    waiters: Waiters,
+    _backoff: PhantomData<fn() -> B>,
 }
 
 impl<T> Allocator<T> {
     pub fn new(capacity: usize) -> Self {
-        let mut storage = Vec::with_capacity(capacity);
+        Self::growable(capacity, capacity)
+    }
 
-        storage.resize_with(capacity, || {
-            Align128(Mutex::new(MaybeUninit::uninit()))
-        });
+    /// Starts out with `capacity` slots and adds segments of `capacity`
+    /// more whenever all of them are in use, up to `max_capacity`.
+    pub fn growable(capacity: usize, max_capacity: usize) -> Self {
+        Self::growable_with_backoff(capacity, max_capacity)
+    }
 
-        let storage = storage.into_boxed_slice();
+    /// Like [`Self::growable`], but spreads the threads' searches for
+    /// a free slot over `shards` evenly spaced starting points instead
+    /// of one per slot.
+    pub fn sharded(
+        capacity: usize,
+        max_capacity: usize,
+        shards: usize,
+    ) -> Self {
+        Self::sharded_with_backoff(capacity, max_capacity, shards)
+    }
+}
 
-        let mut indices = Vec::with_capacity(capacity);
-        indices.resize_with(capacity, Default::default);
+impl<T, B: Backoff> Allocator<T, B> {
+    /// Like [`Allocator::new`], but waits for a slot that is about to
+    /// be unlocked according to `B`.
+    pub fn with_backoff(capacity: usize) -> Self {
+        Self::growable_with_backoff(capacity, capacity)
+    }
 
-        let indices =
-            parking_lot::Mutex::new(indices.into_boxed_slice());
+    pub fn growable_with_backoff(
+        capacity: usize,
+        max_capacity: usize,
+    ) -> Self {
+        Self::sharded_with_backoff(capacity, max_capacity, capacity)
+    }
 
-        Self { storage, indices }
+    pub fn sharded_with_backoff(
+        capacity: usize,
+        max_capacity: usize,
+        shards: usize,
+    ) -> Self {
+        Self {
+            storage: Segments::new(capacity, max_capacity, segment),
+            occupied: AtomicUsize::new(0),
+            shards: Shards::new(shards),
+            waiters: Waiters::new(),
+            _backoff: PhantomData,
+        }
     }
 
     #[track_caller]
     pub fn box_it(&self, value: T) -> Box<'_, T> {
-        self.box_it_with_index(value, 0)
+        self.try_box_it(value).expect("out of reserved memory")
     }
 
-    #[track_caller]
-    fn box_it_with_index(&self, value: T, index: usize) -> Box<'_, T> {
-        let mut guard = self
-            .storage
-            .iter()
-            .cycle()
-            .skip(index)
-            .find_map(|mutex| mutex.try_lock())
-            .unwrap();
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        self.try_box_it_in_shard(value, self.shards.current())
+    }
+
+    fn try_box_it_in_shard(
+        &self,
+        value: T,
+        shard: usize,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        if !self.reserve() {
+            return Err(OutOfMemory(value));
+        }
 
-        guard.write(value);
+        // A slot is guaranteed to be free for us now, but the scan can
+        // still miss it while other threads lock and unlock slots.
+        let mut backoff = B::default();
 
-        Box { guard }
-    }
+        loop {
+            let len = self.storage.len();
+            let index = shard * len / self.shards.count();
 
-    pub fn thread_local(&self) -> AllocatorRef<'_, T> {
-        let mut indices = self.indices.lock();
+            let guard = (0..len)
+                .map(|offset| (index + offset) % len)
+                .map(|i| unsafe { self.storage.get_unchecked(i) })
+                .find_map(|mutex| mutex.try_lock());
 
-        let (index, count) = indices
-            .iter_mut()
-            .enumerate()
-            .min_by_key(|(_, count)| **count)
-            .unwrap();
+            if let Some(mut guard) = guard {
+                guard.write(value);
 
-        *count += 1;
+                return Ok(Box {
+                    guard: ManuallyDrop::new(guard),
+                    occupied: &self.occupied,
+                    _notify: NotifyOnDrop(&self.waiters),
+                });
+            }
 
+            backoff.backoff();
+        }
+    }
+
+    fn reserve(&self) -> bool {
+        let mut occupied = self.occupied.load(Relaxed);
+
+        loop {
+            let len = self.storage.len();
+
+            if occupied < len {
+                match self.occupied.compare_exchange_weak(
+                    occupied,
+                    occupied + 1,
+                    Acquire,
+                    Relaxed,
+                ) {
+                    Ok(_) => return true,
+                    Err(new_occupied) => occupied = new_occupied,
+                }
+            } else {
+                match self.storage.grow(len, segment, |_| {}) {
+                    Growth::Grown | Growth::Raced => {}
+                    Growth::Exhausted => {
+                        occupied = self.occupied.load(Relaxed);
+
+                        if occupied >= len {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns a handle with a shard of its own. Boxes allocated
+    /// without one use the calling thread's shard.
+    pub fn thread_local(&self) -> AllocatorRef<'_, T, B> {
         AllocatorRef {
             allocator: self,
-            index,
+            registration: self.shards.register(),
         }
     }
 }
 
-pub struct AllocatorRef<'allocator, T> {
-    allocator: &'allocator Allocator<T>,
-    index: usize,
+impl<T, B: Backoff> PoolAllocator<T> for Allocator<T, B> {
+    type Box<'a>
+        = Box<'a, T>
+    where
+        Self: 'a;
+
+    fn new(capacity: usize) -> Self {
+        Self::with_backoff(capacity)
+    }
+
+    fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        self.try_box_it(value)
+    }
+
+    fn waiters(&self) -> &Waiters {
+        &self.waiters
+    }
+}
+
+pub struct AllocatorRef<'allocator, T, B = SpinThenYield> {
+    allocator: &'allocator Allocator<T, B>,
+    registration: Registration<'allocator>,
 }
 
-impl<T> AllocatorRef<'_, T> {
+impl<T, B: Backoff> AllocatorRef<'_, T, B> {
     #[track_caller]
     pub fn box_it(&self, value: T) -> Box<'_, T> {
-        self.allocator.box_it_with_index(value, self.index)
+        self.try_box_it(value).expect("out of reserved memory")
     }
-}
 
-impl<T> Drop for AllocatorRef<'_, T> {
-    fn drop(&mut self) {
-        self.allocator.indices.lock()[self.index] -= 1;
+    pub fn try_box_it(
+        &self,
+        value: T,
+    ) -> Result<Box<'_, T>, OutOfMemory<T>> {
+        let shard = self.registration.shard();
+        self.allocator.try_box_it_in_shard(value, shard)
     }
 }
 
+pub type OwnedBox<T> = crate::owned::OwnedBox<Allocator<T>, T>;
+
 pub struct Box<'guard, T> {
-    guard: MutexGuard<'guard, MaybeUninit<T>>,
+    guard: ManuallyDrop<MutexGuard<'guard, MaybeUninit<T>>>,
+    occupied: &'guard AtomicUsize,
+    _notify: NotifyOnDrop<'guard>,
 }
 
 impl<T> Deref for Box<'_, T> {
@@ -168,6 +307,11 @@ impl<T> DerefMut for Box<'_, T> {
 
 impl<T> Drop for Box<'_, T> {
     fn drop(&mut self) {
-        unsafe { self.guard.assume_init_drop() };
+        let value = unsafe { self.guard.assume_init_read() };
+        // Unlocked before the slot is counted as free, so that nobody
+        // who reserves it spins on it, not even `T::drop`.
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        self.occupied.fetch_sub(1, Release);
+        std::mem::drop(value);
     }
 }
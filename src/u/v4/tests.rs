@@ -1,26 +1,110 @@
 use std::mem::drop;
 
+crate::conformance::suite!(
+    super::Allocator::new,
+    Unspecified,
+    Growable(super::Allocator::growable)
+);
+
 #[test]
-fn equality() {
-    let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    assert_eq!(123, *b);
+fn thread_local_handles_use_their_shard() {
+    let a = super::Allocator::<i64>::sharded(4, 4, 2);
+    let first = a.thread_local();
+    let second = a.thread_local();
+    let b = first.box_it(0);
+    let c = second.box_it(1);
+    let distance = (&*c as *const i64 as usize)
+        .wrapping_sub(&*b as *const i64 as usize);
+    assert_eq!(2 * std::mem::size_of::<super::Slot<i64>>(), distance);
+    drop((b, c));
 }
 
 #[test]
-#[should_panic]
-fn panic_when_out_of_memory() {
+fn more_handles_than_u16() {
     let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    let c = a.box_it(234);
-    drop((b, c));
+    let handles: Vec<_> =
+        (0..70_000).map(|_| a.thread_local()).collect();
+    assert_eq!(123, *handles[69_999].box_it(123));
 }
 
 #[test]
-fn memory_reclamation() {
-    let a = super::Allocator::<i64>::new(1);
-    let b = a.box_it(123);
-    drop(b);
-    let c = a.box_it(234);
-    assert_eq!(234, *c);
+fn no_spurious_exhaustion_under_contention() {
+    let a = super::Allocator::<usize>::new(8);
+
+    std::thread::scope(|s| {
+        for t in 0..8 {
+            let a = &a;
+
+            s.spawn(move || {
+                for i in 0..10_000 {
+                    let b = a.try_box_it(t + i).unwrap();
+                    assert_eq!(t + i, *b);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn stress_more_threads_than_slots() {
+    let a = super::Allocator::<usize>::new(4);
+    let in_use = std::sync::atomic::AtomicUsize::new(0);
+
+    std::thread::scope(|s| {
+        for t in 0..16 {
+            let (a, in_use) = (&a, &in_use);
+
+            s.spawn(move || {
+                let handle = a.thread_local();
+
+                for i in 0..5_000 {
+                    let result = if i % 2 == 0 {
+                        handle.try_box_it(t)
+                    } else {
+                        a.try_box_it(t)
+                    };
+
+                    if let Ok(b) = result {
+                        let n = in_use.fetch_add(1, super::Relaxed);
+                        assert!(n < 4);
+                        assert_eq!(t, *b);
+                        in_use.fetch_sub(1, super::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+
+    let boxes: Vec<_> = (0..4).map(|i| a.box_it(i)).collect();
+    assert!(a.try_box_it(4).is_err());
+    drop(boxes);
+}
+
+#[test]
+fn drop_can_box_into_its_own_pool() {
+    struct Reenter(Option<&'static super::Allocator<Reenter>>);
+
+    impl Drop for Reenter {
+        fn drop(&mut self) {
+            if let Some(a) = self.0 {
+                let b = a.try_box_it(Reenter(None));
+                assert!(b.is_ok());
+                assert!(a.try_box_it(Reenter(None)).is_err());
+            }
+        }
+    }
+
+    let a: &'static _ = std::boxed::Box::leak(std::boxed::Box::new(
+        super::Allocator::new(1),
+    ));
+    let (done, finished) = std::sync::mpsc::channel();
+
+    // Spins forever if the slot is still locked when `T::drop` runs.
+    std::thread::spawn(move || {
+        drop(a.box_it(Reenter(Some(a))));
+        done.send(()).unwrap();
+    });
+
+    let timeout = std::time::Duration::from_secs(10);
+    finished.recv_timeout(timeout).unwrap();
 }
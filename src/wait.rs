@@ -0,0 +1,268 @@
+#[cfg(test)]
+mod tests;
+
+use crate::error::OutOfMemory;
+use crate::pool_allocator::PoolAllocator;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::fence;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::time::Instant;
+
+/// Threads blocked in `box_it_wait` / `box_it_timeout` and tasks
+/// awaiting `box_it_async`, woken whenever a `Box` returns its slot to
+/// the pool.
+#[derive(Debug, Default)]
+pub struct Waiters {
+    sleepers: AtomicUsize,
+    epoch: Mutex<u64>,
+    condvar: Condvar,
+    tasks: AtomicUsize,
+    wakers: Mutex<Wakers>,
+}
+
+#[derive(Debug, Default)]
+struct Wakers {
+    next_key: u64,
+    queue: VecDeque<(u64, Waker)>,
+}
+
+impl Waiters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Must be called *after* the slot has been released, otherwise a
+    /// woken thread may retry too early and miss the wake-up.
+    pub fn notify(&self) {
+        self.notify_many(1);
+    }
+
+    /// Like [`notify`](Self::notify), for `count` slots released at
+    /// once, so that up to `count` tasks are woken instead of one.
+    pub fn notify_many(&self, count: usize) {
+        fence(SeqCst);
+
+        if self.sleepers.load(SeqCst) != 0 {
+            *self.lock() += 1;
+            self.condvar.notify_all();
+        }
+
+        for _ in 0..count {
+            if self.tasks.load(SeqCst) == 0 {
+                break;
+            }
+
+            self.wake_one();
+        }
+    }
+
+    /// Whether no thread or task is waiting for a slot right now.
+    pub(crate) fn is_empty(&self) -> bool {
+        fence(SeqCst);
+        self.sleepers.load(SeqCst) == 0 && self.tasks.load(SeqCst) == 0
+    }
+
+    pub(crate) fn wait<T, B>(
+        &self,
+        mut value: T,
+        deadline: Option<Instant>,
+        mut try_box_it: impl FnMut(T) -> Result<B, OutOfMemory<T>>,
+    ) -> Result<B, OutOfMemory<T>> {
+        self.sleepers.fetch_add(1, SeqCst);
+
+        let result = loop {
+            let epoch = *self.lock();
+            fence(SeqCst);
+
+            match try_box_it(value) {
+                Ok(b) => break Ok(b),
+                Err(OutOfMemory(v)) => value = v,
+            }
+
+            if !self.park(epoch, deadline) {
+                break Err(OutOfMemory(value));
+            }
+        };
+
+        self.sleepers.fetch_sub(1, SeqCst);
+        result
+    }
+
+    fn park(&self, epoch: u64, deadline: Option<Instant>) -> bool {
+        let mut guard = self.lock();
+
+        while *guard == epoch {
+            guard = match deadline {
+                None => self
+                    .condvar
+                    .wait(guard)
+                    .unwrap_or_else(PoisonError::into_inner),
+                Some(deadline) => {
+                    let now = Instant::now();
+
+                    if deadline <= now {
+                        return false;
+                    }
+
+                    self.condvar
+                        .wait_timeout(guard, deadline - now)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+
+        true
+    }
+
+    fn lock(&self) -> MutexGuard<'_, u64> {
+        self.epoch.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_wakers(&self) -> MutexGuard<'_, Wakers> {
+        self.wakers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wake_one(&self) {
+        let waker = {
+            let mut wakers = self.lock_wakers();
+            let waker = wakers.queue.pop_front();
+            self.tasks.store(wakers.queue.len(), SeqCst);
+            waker
+        };
+
+        if let Some((_, waker)) = waker {
+            waker.wake();
+        }
+    }
+
+    fn register(&self, key: Option<u64>, waker: &Waker) -> u64 {
+        let mut wakers = self.lock_wakers();
+
+        if let Some(key) = key {
+            if let Some((_, queued)) =
+                wakers.queue.iter_mut().find(|(k, _)| *k == key)
+            {
+                queued.clone_from(waker);
+                return key;
+            }
+        }
+
+        let key = wakers.next_key;
+        wakers.next_key += 1;
+        wakers.queue.push_back((key, waker.clone()));
+        self.tasks.store(wakers.queue.len(), SeqCst);
+        key
+    }
+
+    /// Returns `true` if the registration was already woken, i.e. it
+    /// has been handed a wake-up that it now has to consume or forward.
+    fn deregister(&self, key: u64) -> bool {
+        let mut wakers = self.lock_wakers();
+
+        match wakers.queue.iter().position(|(k, _)| *k == key) {
+            Some(position) => {
+                wakers.queue.remove(position);
+                self.tasks.store(wakers.queue.len(), SeqCst);
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+/// Future returned by [`PoolAllocator::box_it_async`].
+///
+/// Dropping it before it resolves is safe: a wake-up it has received
+/// but not used is passed on to the next waiting task.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BoxItFuture<'a, A: PoolAllocator<T>, T> {
+    allocator: &'a A,
+    value: Option<T>,
+    key: Option<u64>,
+}
+
+impl<'a, A: PoolAllocator<T>, T> BoxItFuture<'a, A, T> {
+    pub(crate) fn new(allocator: &'a A, value: T) -> Self {
+        Self {
+            allocator,
+            value: Some(value),
+            key: None,
+        }
+    }
+
+    fn ready(&mut self, b: A::Box<'a>) -> Poll<A::Box<'a>> {
+        if let Some(key) = self.key.take() {
+            self.allocator.waiters().deregister(key);
+        }
+
+        Poll::Ready(b)
+    }
+}
+
+// The value is never pinned; it is only moved in and out of `value`.
+impl<A: PoolAllocator<T>, T> Unpin for BoxItFuture<'_, A, T> {}
+
+impl<'a, A: PoolAllocator<T>, T> Future for BoxItFuture<'a, A, T> {
+    type Output = A::Box<'a>;
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let allocator = this.allocator;
+
+        let value = this.value.take().expect("polled after completion");
+
+        let value = match allocator.try_box_it(value) {
+            Ok(b) => return this.ready(b),
+            Err(OutOfMemory(value)) => value,
+        };
+
+        this.key =
+            Some(allocator.waiters().register(this.key, cx.waker()));
+        fence(SeqCst);
+
+        match allocator.try_box_it(value) {
+            Ok(b) => this.ready(b),
+            Err(OutOfMemory(value)) => {
+                this.value = Some(value);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<A: PoolAllocator<T>, T> Drop for BoxItFuture<'_, A, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            let waiters = self.allocator.waiters();
+
+            if waiters.deregister(key) {
+                waiters.wake_one();
+            }
+        }
+    }
+}
+
+/// Notifies the waiters when dropped. Boxes whose slot is released by
+/// one of their own fields keep this as their last field, so that it
+/// runs after the slot is free again.
+pub(crate) struct NotifyOnDrop<'a>(pub(crate) &'a Waiters);
+
+impl Drop for NotifyOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.notify();
+    }
+}
@@ -0,0 +1,141 @@
+use crate::backoff::ExponentialSpin;
+use crate::backoff::SpinThenPark;
+use crate::executor::block_on;
+use crate::pool_allocator::PoolAllocator;
+use crate::s;
+use crate::u;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Wake;
+use std::task::Waker;
+use std::time::Duration;
+
+fn churn<A: PoolAllocator<usize> + Sync>() {
+    let a = A::new(2);
+
+    std::thread::scope(|s| {
+        for id in 0..8 {
+            let a = &a;
+
+            s.spawn(move || {
+                for _ in 0..1_000 {
+                    let b = a.box_it_wait(id);
+                    assert_eq!(id, *b);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn no_lost_wake_ups() {
+    churn::<s::basic::std::Allocator<usize>>();
+    churn::<s::advanced::v1::Allocator<usize>>();
+    churn::<s::advanced::v3::Allocator<usize, ExponentialSpin>>();
+    churn::<u::v3::Allocator<usize>>();
+    churn::<u::v3::Allocator<usize, SpinThenPark>>();
+    churn::<u::v4::Allocator<usize>>();
+}
+
+struct CountWakes(AtomicUsize);
+
+impl Wake for CountWakes {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, SeqCst);
+    }
+}
+
+fn poll_once<F: Future + Unpin>(
+    future: &mut F,
+    wakes: &Arc<CountWakes>,
+) -> Poll<F::Output> {
+    let waker = Waker::from(wakes.clone());
+    Pin::new(future).poll(&mut Context::from_waker(&waker))
+}
+
+#[test]
+fn notify_many_wakes_one_task_per_slot() {
+    let waiters = super::Waiters::new();
+    let wakes: Vec<_> = (0..3)
+        .map(|_| Arc::new(CountWakes(AtomicUsize::new(0))))
+        .collect();
+
+    for w in &wakes {
+        waiters.register(None, &Waker::from(w.clone()));
+    }
+
+    waiters.notify_many(2);
+    let woken: Vec<_> =
+        wakes.iter().map(|w| w.0.load(SeqCst)).collect();
+    assert_eq!(vec![1, 1, 0], woken);
+}
+
+fn async_wait_for_release<A: PoolAllocator<i64> + Sync>() {
+    let a = A::new(1);
+    let b = a.box_it(123);
+
+    std::thread::scope(|s| {
+        let waiter = s.spawn(|| *block_on(a.box_it_async(234)));
+        std::thread::sleep(Duration::from_millis(10));
+        drop(b);
+        assert_eq!(234, waiter.join().unwrap());
+    });
+}
+
+fn cancellation_forwards_wake_up<A: PoolAllocator<i64>>() {
+    let a = A::new(1);
+    let b = a.box_it(123);
+    let (wakes_1, wakes_2) = (
+        Arc::new(CountWakes(AtomicUsize::new(0))),
+        Arc::new(CountWakes(AtomicUsize::new(0))),
+    );
+
+    let mut first = a.box_it_async(234);
+    let mut second = a.box_it_async(345);
+    assert!(poll_once(&mut first, &wakes_1).is_pending());
+    assert!(poll_once(&mut second, &wakes_2).is_pending());
+
+    drop(b);
+    assert_eq!(1, wakes_1.0.load(SeqCst));
+    assert_eq!(0, wakes_2.0.load(SeqCst));
+
+    drop(first);
+    assert_eq!(1, wakes_2.0.load(SeqCst));
+
+    match poll_once(&mut second, &wakes_2) {
+        Poll::Ready(b) => assert_eq!(345, *b),
+        Poll::Pending => panic!("slot was released"),
+    };
+}
+
+fn async_churn<A: PoolAllocator<usize> + Sync>() {
+    let a = A::new(2);
+
+    std::thread::scope(|s| {
+        for id in 0..8 {
+            let a = &a;
+
+            s.spawn(move || {
+                for _ in 0..1_000 {
+                    let b = block_on(a.box_it_async(id));
+                    assert_eq!(id, *b);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn async_acquisition() {
+    async_wait_for_release::<s::advanced::v3::Allocator<i64>>();
+    async_wait_for_release::<u::v3::Allocator<i64>>();
+    cancellation_forwards_wake_up::<s::advanced::v3::Allocator<i64>>();
+    cancellation_forwards_wake_up::<u::v3::Allocator<i64>>();
+    async_churn::<s::advanced::v3::Allocator<usize>>();
+    async_churn::<u::v3::Allocator<usize>>();
+}
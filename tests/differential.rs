@@ -0,0 +1,253 @@
+//! Runs random scripts of boxing, dropping and mutating values against
+//! every allocator and against a model with one `Vec<Option<u64>>` of
+//! boxes per thread. Each step of a script names the thread that runs
+//! it, and the threads run one step at a time in that order, so a
+//! failing interleaving replays deterministically and shrinks like any
+//! other input.
+#![cfg(all(feature = "std", not(loom)))]
+
+use allocator::pool_allocator::PoolAllocator;
+use allocator::s;
+use allocator::u;
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::thread;
+
+const MAX_CAPACITY: usize = 8;
+const MAX_THREADS: usize = 3;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Box(u64),
+    Drop(usize),
+    Add(usize, u64),
+}
+
+#[derive(Clone, Debug)]
+struct Script {
+    capacity: usize,
+    steps: Vec<(usize, Op)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Done,
+    OutOfMemory(u64),
+}
+
+// What a thread reports after every step: the outcome, and the value
+// and address of each of its boxes.
+type Report = (Outcome, Vec<Option<(u64, usize)>>);
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        any::<u64>().prop_map(Op::Box),
+        (0..MAX_CAPACITY).prop_map(Op::Drop),
+        (0..MAX_CAPACITY, any::<u64>())
+            .prop_map(|(i, delta)| Op::Add(i, delta)),
+    ]
+}
+
+fn script() -> impl Strategy<Value = Script> {
+    let steps = (0..MAX_THREADS, op());
+
+    (1..=MAX_CAPACITY, prop::collection::vec(steps, 0..64))
+        .prop_map(|(capacity, steps)| Script { capacity, steps })
+}
+
+struct Model {
+    capacity: usize,
+    boxes: Vec<Vec<Option<u64>>>,
+}
+
+impl Model {
+    fn new(capacity: usize, threads: usize) -> Self {
+        Self {
+            capacity,
+            boxes: vec![vec![None; capacity]; threads],
+        }
+    }
+
+    fn live(&self) -> usize {
+        self.boxes.iter().flatten().flatten().count()
+    }
+
+    fn apply(&mut self, thread: usize, op: Op) -> Outcome {
+        let capacity = self.capacity;
+        let live = self.live();
+        let boxes = &mut self.boxes[thread];
+
+        match op {
+            Op::Box(value) if live < capacity => {
+                let free = boxes.iter().position(Option::is_none);
+                boxes[free.unwrap()] = Some(value);
+            }
+            Op::Box(value) => return Outcome::OutOfMemory(value),
+            Op::Drop(i) => boxes[i % capacity] = None,
+            Op::Add(i, delta) => {
+                if let Some(value) = &mut boxes[i % capacity] {
+                    *value = value.wrapping_add(delta);
+                }
+            }
+        }
+
+        Outcome::Done
+    }
+}
+
+// Runs the steps a thread receives on its own boxes, which it keeps
+// in the same places as the model does.
+fn worker<A: PoolAllocator<u64>>(
+    allocator: &A,
+    capacity: usize,
+    steps: mpsc::Receiver<Op>,
+    reports: mpsc::Sender<Report>,
+) {
+    let mut boxes: Vec<Option<A::Box<'_>>> =
+        (0..capacity).map(|_| None).collect();
+
+    for op in steps {
+        let outcome = match op {
+            Op::Box(value) => match allocator.try_box_it(value) {
+                Ok(b) => {
+                    let free = boxes.iter().position(Option::is_none);
+                    boxes[free.unwrap()] = Some(b);
+                    Outcome::Done
+                }
+                Err(e) => Outcome::OutOfMemory(e.into_inner()),
+            },
+            Op::Drop(i) => {
+                boxes[i % capacity] = None;
+                Outcome::Done
+            }
+            Op::Add(i, delta) => {
+                if let Some(b) = &mut boxes[i % capacity] {
+                    **b = b.wrapping_add(delta);
+                }
+
+                Outcome::Done
+            }
+        };
+
+        let snapshot = boxes
+            .iter()
+            .map(|b| {
+                b.as_ref().map(|b| (**b, &**b as *const u64 as usize))
+            })
+            .collect();
+
+        if reports.send((outcome, snapshot)).is_err() {
+            break;
+        }
+    }
+}
+
+fn check<A>(script: &Script) -> Result<(), TestCaseError>
+where
+    A: PoolAllocator<u64> + Sync,
+{
+    let allocator = A::new(script.capacity);
+    let mut model = Model::new(script.capacity, MAX_THREADS);
+    let mut addresses = vec![vec![None; script.capacity]; MAX_THREADS];
+
+    thread::scope(|s| {
+        let threads: Vec<_> = (0..MAX_THREADS)
+            .map(|_| {
+                let (step, steps) = mpsc::channel();
+                let (reports, report) = mpsc::channel();
+                let allocator = &allocator;
+                let capacity = script.capacity;
+                s.spawn(move || {
+                    worker(allocator, capacity, steps, reports)
+                });
+                (step, report)
+            })
+            .collect();
+
+        for &(thread, op) in &script.steps {
+            let (step, report) = &threads[thread];
+            step.send(op).unwrap();
+            let (outcome, snapshot) = report.recv().unwrap();
+            prop_assert_eq!(model.apply(thread, op), outcome);
+
+            let values: Vec<_> = snapshot
+                .iter()
+                .map(|b| b.map(|(value, _)| value))
+                .collect();
+            prop_assert_eq!(&model.boxes[thread], &values);
+
+            addresses[thread] = snapshot
+                .iter()
+                .map(|b| b.map(|(_, address)| address))
+                .collect();
+            let live: Vec<_> =
+                addresses.iter().flatten().flatten().collect();
+            let unique: HashSet<_> = live.iter().collect();
+            prop_assert_eq!(live.len(), unique.len(), "boxes alias");
+        }
+
+        Ok(())
+    })
+}
+
+proptest! {
+    #[test]
+    fn s_basic_std(script in script()) {
+        check::<s::basic::std::Allocator<u64>>(&script)?;
+    }
+
+    #[cfg(feature = "antidote")]
+    #[test]
+    fn s_basic_antidote(script in script()) {
+        check::<s::basic::antidote::Allocator<u64>>(&script)?;
+    }
+
+    #[cfg(feature = "parking_lot")]
+    #[test]
+    fn s_basic_parking_lot(script in script()) {
+        check::<s::basic::parking_lot::Allocator<u64>>(&script)?;
+    }
+
+    #[cfg(feature = "simple_mutex")]
+    #[test]
+    fn s_basic_simple_mutex(script in script()) {
+        check::<s::basic::simple_mutex::Allocator<u64>>(&script)?;
+    }
+
+    #[test]
+    fn s_advanced_v1(script in script()) {
+        check::<s::advanced::v1::Allocator<u64>>(&script)?;
+    }
+
+    #[test]
+    fn s_advanced_v2(script in script()) {
+        check::<s::advanced::v2::Allocator<u64>>(&script)?;
+    }
+
+    #[test]
+    fn s_advanced_v3(script in script()) {
+        check::<s::advanced::v3::Allocator<u64>>(&script)?;
+    }
+
+    #[test]
+    fn u_v1(script in script()) {
+        check::<u::v1::Allocator<u64>>(&script)?;
+    }
+
+    #[test]
+    fn u_v2(script in script()) {
+        check::<u::v2::Allocator<u64>>(&script)?;
+    }
+
+    #[test]
+    fn u_v3(script in script()) {
+        check::<u::v3::Allocator<u64>>(&script)?;
+    }
+
+    #[test]
+    fn u_v4(script in script()) {
+        check::<u::v4::Allocator<u64>>(&script)?;
+    }
+}
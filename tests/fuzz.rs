@@ -0,0 +1,122 @@
+//! Runs the fuzz targets in `fuzz/` as ordinary tests: each replays
+//! the target's corpus and crash artifacts, if there are any, after a
+//! fixed set of pseudo-random inputs.
+#![cfg(all(feature = "std", not(loom)))]
+
+#[path = "../fuzz/harness.rs"]
+mod harness;
+
+use allocator::pool_allocator::PoolAllocator;
+use allocator::s;
+use allocator::u;
+use harness::Tracked;
+use std::fs;
+use std::panic;
+use std::path::Path;
+
+const SEEDS: u64 = 256;
+
+// xorshift64*, so that the inputs do not depend on any crate.
+fn seed(n: u64) -> Vec<u8> {
+    let mut state =
+        n.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+
+    let mut next = || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_f491_4f6c_dd1d).to_le_bytes()[7]
+    };
+
+    let len = usize::from(next());
+    (0..len).map(|_| next()).collect()
+}
+
+fn replay<A: PoolAllocator<Tracked>>(target: &str) {
+    for n in 0..SEEDS {
+        harness::run::<A>(&seed(n));
+    }
+
+    let fuzz = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz");
+
+    for dir in ["corpus", "artifacts"] {
+        let entries = match fs::read_dir(fuzz.join(dir).join(target)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries {
+            let path = entry.unwrap().path();
+            let data = fs::read(&path).unwrap();
+
+            if panic::catch_unwind(|| harness::run::<A>(&data)).is_err()
+            {
+                panic!("{} fails", path.display());
+            }
+        }
+    }
+}
+
+#[test]
+fn s_basic_std() {
+    replay::<s::basic::std::Allocator<Tracked>>("s_basic_std");
+}
+
+#[cfg(feature = "antidote")]
+#[test]
+fn s_basic_antidote() {
+    replay::<s::basic::antidote::Allocator<Tracked>>(
+        "s_basic_antidote",
+    );
+}
+
+#[cfg(feature = "parking_lot")]
+#[test]
+fn s_basic_parking_lot() {
+    replay::<s::basic::parking_lot::Allocator<Tracked>>(
+        "s_basic_parking_lot",
+    );
+}
+
+#[cfg(feature = "simple_mutex")]
+#[test]
+fn s_basic_simple_mutex() {
+    replay::<s::basic::simple_mutex::Allocator<Tracked>>(
+        "s_basic_simple_mutex",
+    );
+}
+
+#[test]
+fn s_advanced_v1() {
+    replay::<s::advanced::v1::Allocator<Tracked>>("s_advanced_v1");
+}
+
+#[test]
+fn s_advanced_v2() {
+    replay::<s::advanced::v2::Allocator<Tracked>>("s_advanced_v2");
+}
+
+#[test]
+fn s_advanced_v3() {
+    replay::<s::advanced::v3::Allocator<Tracked>>("s_advanced_v3");
+}
+
+#[test]
+fn u_v1() {
+    replay::<u::v1::Allocator<Tracked>>("u_v1");
+}
+
+#[test]
+fn u_v2() {
+    replay::<u::v2::Allocator<Tracked>>("u_v2");
+}
+
+#[test]
+fn u_v3() {
+    replay::<u::v3::Allocator<Tracked>>("u_v3");
+}
+
+#[test]
+fn u_v4() {
+    replay::<u::v4::Allocator<Tracked>>("u_v4");
+}
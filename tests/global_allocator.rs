@@ -0,0 +1,41 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use allocator::u::v3::global::SizeClasses;
+use std::collections::BTreeMap;
+use std::thread;
+
+#[global_allocator]
+static GLOBAL: SizeClasses = SizeClasses::with_capacity(1024);
+
+#[test]
+fn small_boxes_come_from_the_pools() {
+    let b = Box::new(123_u64);
+    assert!(GLOBAL.owns(&*b as *const u64 as *const u8));
+    let v = vec![0_u8; 4096];
+    assert!(!GLOBAL.owns(v.as_ptr()));
+}
+
+#[test]
+fn collections_across_threads() {
+    let threads: Vec<_> = (0..8)
+        .map(|t| {
+            thread::spawn(move || {
+                let mut map = BTreeMap::new();
+
+                for i in 0..10_000 {
+                    map.insert(i, format!("{}-{}", t, i));
+
+                    if i % 3 == 0 {
+                        map.remove(&(i / 2));
+                    }
+                }
+
+                map.values().map(String::len).sum::<usize>()
+            })
+        })
+        .collect();
+
+    let lens: Vec<_> =
+        threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert!(lens.iter().all(|&len| len == lens[0]));
+}
@@ -0,0 +1,136 @@
+//! Model checks of the lock-free variants. Run them with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --release --test loom
+//! ```
+#![cfg(loom)]
+
+use allocator::pool_allocator::PoolAllocator;
+use loom::model::Builder;
+use loom::sync::Arc;
+use loom::thread;
+
+type UnsafeV3 = allocator::u::v3::Allocator<usize>;
+type UnsafeV4 = allocator::u::v4::Allocator<usize>;
+type SafeV3 = allocator::s::advanced::v3::Allocator<usize>;
+
+// Every thread boxes its own id while the others do the same, so two
+// threads handed the same slot overwrite each other's value.
+fn box_and_drop<A>(threads: usize)
+where
+    A: PoolAllocator<usize> + Send + Sync + 'static,
+{
+    let pool = Arc::new(A::new(threads));
+
+    let handles: Vec<_> = (0..threads)
+        .map(|id| {
+            let pool = pool.clone();
+            thread::spawn(move || {
+                let b = pool.box_it(id);
+                thread::yield_now();
+                assert_eq!(id, *b);
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    drain(&*pool, threads);
+}
+
+// More threads than slots, so that boxes are dropped while another
+// thread retries.
+fn contended<A>(capacity: usize, threads: usize)
+where
+    A: PoolAllocator<usize> + Send + Sync + 'static,
+{
+    let pool = Arc::new(A::new(capacity));
+
+    let handles: Vec<_> = (0..threads)
+        .map(|id| {
+            let pool = pool.clone();
+            thread::spawn(move || {
+                let mut value = id;
+
+                let b = loop {
+                    match pool.try_box_it(value) {
+                        Ok(b) => break b,
+                        Err(e) => value = e.0,
+                    }
+
+                    thread::yield_now();
+                };
+
+                thread::yield_now();
+                assert_eq!(id, *b);
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    drain(&*pool, capacity);
+}
+
+// Every slot is free again once all boxes are gone.
+fn drain<A: PoolAllocator<usize>>(pool: &A, capacity: usize) {
+    let boxes: Vec<_> = (0..capacity).map(|i| pool.box_it(i)).collect();
+    assert!(pool.try_box_it(capacity).is_err());
+
+    for (i, b) in boxes.iter().enumerate() {
+        assert_eq!(i, **b);
+    }
+}
+
+fn bounded(f: impl Fn() + Sync + Send + 'static) {
+    let mut builder = Builder::new();
+    builder.preemption_bound = Some(2);
+    builder.check(f);
+}
+
+#[test]
+fn unsafe_v3_box_and_drop() {
+    loom::model(|| box_and_drop::<UnsafeV3>(2));
+}
+
+#[test]
+fn unsafe_v3_contended() {
+    loom::model(|| contended::<UnsafeV3>(1, 2));
+}
+
+#[test]
+fn unsafe_v3_three_threads() {
+    bounded(|| contended::<UnsafeV3>(2, 3));
+}
+
+#[test]
+fn unsafe_v4_box_and_drop() {
+    loom::model(|| box_and_drop::<UnsafeV4>(2));
+}
+
+#[test]
+fn unsafe_v4_contended() {
+    loom::model(|| contended::<UnsafeV4>(1, 2));
+}
+
+#[test]
+fn unsafe_v4_three_threads() {
+    bounded(|| contended::<UnsafeV4>(2, 3));
+}
+
+// A third thread is out of reach here: the slots' `std::sync::Mutex`
+// is invisible to loom, so a thread spinning on a locked slot blows up
+// the number of schedules to explore.
+#[test]
+fn safe_v3_box_and_drop() {
+    loom::model(|| box_and_drop::<SafeV3>(2));
+}
+
+#[test]
+fn safe_v3_contended() {
+    loom::model(|| contended::<SafeV3>(1, 2));
+}